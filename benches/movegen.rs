@@ -0,0 +1,58 @@
+//! Compares the bitboard move generator with the original square by square one.
+//! Run with `cargo bench --features bench`.
+
+use chess::Board;
+use std::time::{Duration, Instant};
+
+fn mailbox_perft(board: &Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    board
+        .mailbox_legal_moves(board.turn())
+        .into_iter()
+        .map(|m| {
+            let mut after = *board;
+            after.make_move(m);
+            mailbox_perft(&after, depth - 1)
+        })
+        .sum()
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, start.elapsed())
+}
+
+fn main() {
+    for (name, fen, depth) in [
+        (
+            "start",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            4,
+        ),
+        (
+            "kiwipete",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            3,
+        ),
+        ("endgame", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5),
+    ] {
+        let board = Board::from_fen(fen).unwrap();
+        let (nodes, fast) = time(|| board.perft(depth));
+        let (slow_nodes, slow) = time(|| mailbox_perft(&board, depth));
+        assert_eq!(nodes, slow_nodes, "the generators disagree on {}", name);
+        println!(
+            "{:<9} perft {} {:>8} nodes  bitboards {:>8.1?} ({:>5.0} knps)  mailbox {:>8.1?} ({:>5.0} knps)  {:.1}x faster",
+            name,
+            depth,
+            nodes,
+            fast,
+            nodes as f64 / fast.as_secs_f64() / 1000.0,
+            slow,
+            nodes as f64 / slow.as_secs_f64() / 1000.0,
+            slow.as_secs_f64() / fast.as_secs_f64(),
+        );
+    }
+}
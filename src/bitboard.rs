@@ -0,0 +1,480 @@
+use crate::moves::*;
+use crate::pieces::*;
+use crate::square::Square;
+use crate::{Board, Position};
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+/// A set of squares, one bit for each in `Square::index` order
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Self = Self(0);
+
+    pub fn contains(self, sq: Square) -> bool {
+        self.0 & (1 << sq.index()) != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// The lowest square in the set, starting from a1
+    pub fn first(self) -> Option<Square> {
+        Square::from_index(self.0.trailing_zeros() as usize)
+    }
+}
+
+impl From<Square> for Bitboard {
+    fn from(sq: Square) -> Self {
+        Self(1 << sq.index())
+    }
+}
+
+/// Takes squares out of the set from a1 upwards
+impl Iterator for Bitboard {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        let sq = self.first()?;
+        self.0 &= self.0 - 1;
+        Some(sq)
+    }
+}
+
+macro_rules! bit_ops {
+    ($($op:ident $f:ident $assign:ident $g:ident),*) => {
+        $(
+            impl $op for Bitboard {
+                type Output = Self;
+
+                fn $f(self, other: Self) -> Self {
+                    Self($op::$f(self.0, other.0))
+                }
+            }
+
+            impl $assign for Bitboard {
+                fn $g(&mut self, other: Self) {
+                    $assign::$g(&mut self.0, other.0)
+                }
+            }
+        )*
+    };
+}
+
+bit_ops!(
+    BitAnd bitand BitAndAssign bitand_assign,
+    BitOr bitor BitOrAssign bitor_assign,
+    BitXor bitxor BitXorAssign bitxor_assign
+);
+
+impl Not for Bitboard {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+/// Every square one of `steps` away from each square
+const fn step_table(steps: &[(i32, i32)]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut sq = 0;
+    while sq < 64 {
+        let (x, y) = ((sq % 8) as i32, (sq / 8) as i32);
+        let mut i = 0;
+        while i < steps.len() {
+            let (x, y) = (x + steps[i].0, y + steps[i].1);
+            if x >= 0 && x < 8 && y >= 0 && y < 8 {
+                table[sq] |= 1 << (y * 8 + x);
+            }
+            i += 1;
+        }
+        sq += 1;
+    }
+    table
+}
+
+/// Every square from each square to the edge of the board going in one direction
+const fn ray_table(dx: i32, dy: i32) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut sq = 0;
+    while sq < 64 {
+        let (mut x, mut y) = ((sq % 8) as i32 + dx, (sq / 8) as i32 + dy);
+        while x >= 0 && x < 8 && y >= 0 && y < 8 {
+            table[sq] |= 1 << (y * 8 + x);
+            x += dx;
+            y += dy;
+        }
+        sq += 1;
+    }
+    table
+}
+
+const KNIGHT_ATTACKS: [u64; 64] = step_table(&[
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+]);
+const KING_ATTACKS: [u64; 64] = step_table(&[
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+]);
+/// The squares a pawn of each color takes on, white first
+const PAWN_ATTACKS: [[u64; 64]; 2] = [
+    step_table(&[(-1, 1), (1, 1)]),
+    step_table(&[(-1, -1), (1, -1)]),
+];
+/// Rays going north, north east and so on clockwise round to north west
+const RAYS: [[u64; 64]; 8] = [
+    ray_table(0, 1),
+    ray_table(1, 1),
+    ray_table(1, 0),
+    ray_table(1, -1),
+    ray_table(0, -1),
+    ray_table(-1, -1),
+    ray_table(-1, 0),
+    ray_table(-1, 1),
+];
+const ROOK_RAYS: [usize; 4] = [0, 2, 4, 6];
+const BISHOP_RAYS: [usize; 4] = [1, 3, 5, 7];
+
+/// The squares along a ray up to and including the first piece in the way
+fn ray_attacks(dir: usize, sq: Square, occupied: Bitboard) -> u64 {
+    let ray = RAYS[dir][sq.index()];
+    let blockers = ray & occupied.0;
+    if blockers == 0 {
+        return ray;
+    }
+    // rays going up the board meet their lowest blocker first, the rest their highest
+    let first = if matches!(dir, 0 | 1 | 2 | 7) {
+        blockers.trailing_zeros()
+    } else {
+        63 - blockers.leading_zeros()
+    };
+    ray ^ RAYS[dir][first as usize]
+}
+
+pub fn knight_attacks(sq: Square) -> Bitboard {
+    Bitboard(KNIGHT_ATTACKS[sq.index()])
+}
+
+pub fn king_attacks(sq: Square) -> Bitboard {
+    Bitboard(KING_ATTACKS[sq.index()])
+}
+
+/// The squares a pawn of `color` on `sq` could capture on
+pub fn pawn_attacks(color: Color, sq: Square) -> Bitboard {
+    Bitboard(PAWN_ATTACKS[color_index(color)][sq.index()])
+}
+
+pub fn rook_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    Bitboard(
+        ROOK_RAYS
+            .iter()
+            .fold(0, |a, &d| a | ray_attacks(d, sq, occupied)),
+    )
+}
+
+pub fn bishop_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    Bitboard(
+        BISHOP_RAYS
+            .iter()
+            .fold(0, |a, &d| a | ray_attacks(d, sq, occupied)),
+    )
+}
+
+pub fn queen_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    rook_attacks(sq, occupied) | bishop_attacks(sq, occupied)
+}
+
+pub(crate) fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+pub(crate) fn type_index(typ: PieceType) -> usize {
+    match typ {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    }
+}
+
+pub(crate) fn square(pos: Position) -> Square {
+    Square::new(pos.0, pos.1).expect("moves stay on the board")
+}
+
+/// Where every piece stands, as one set for each piece type and one for each color
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Bitboards {
+    pieces: [Bitboard; 6],
+    colors: [Bitboard; 2],
+}
+
+impl Bitboards {
+    pub fn new(board: &Board) -> Self {
+        let mut bb = Self::default();
+        for sq in Square::all() {
+            if let Some(p) = board.piece_at(sq) {
+                bb.pieces[type_index(p.typ)] |= sq.into();
+                bb.colors[color_index(p.color)] |= sq.into();
+            }
+        }
+        bb
+    }
+
+    /// Add `piece` on `sq` if it is not there, or take it away if it is
+    pub(crate) fn toggle(&mut self, sq: Square, piece: Piece) {
+        self.pieces[type_index(piece.typ)] ^= sq.into();
+        self.colors[color_index(piece.color)] ^= sq.into();
+    }
+
+    pub fn pieces(&self, color: Color, typ: PieceType) -> Bitboard {
+        self.pieces[type_index(typ)] & self.colors[color_index(color)]
+    }
+
+    pub fn color(&self, color: Color) -> Bitboard {
+        self.colors[color_index(color)]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    /// The squares `piece` on `sq` attacks, including ones held by its own side
+    pub fn attacks(&self, sq: Square, piece: Piece) -> Bitboard {
+        match piece.typ {
+            PieceType::Pawn => pawn_attacks(piece.color, sq),
+            PieceType::Knight => knight_attacks(sq),
+            PieceType::Bishop => bishop_attacks(sq, self.occupied()),
+            PieceType::Rook => rook_attacks(sq, self.occupied()),
+            PieceType::Queen => queen_attacks(sq, self.occupied()),
+            PieceType::King => king_attacks(sq),
+        }
+    }
+
+    /// Every piece of `by` attacking `sq`. Pinned pieces still count.
+    pub fn attackers(&self, sq: Square, by: Color) -> Bitboard {
+        self.attackers_with(sq, self.occupied()) & self.color(by)
+    }
+
+    /// Pieces of either color attacking `sq` as if only the squares in `occupied`
+    /// were filled, so sliders lined up behind pieces already gone count too
+    pub fn attackers_with(&self, sq: Square, occupied: Bitboard) -> Bitboard {
+        let pawns = self.pieces[type_index(PieceType::Pawn)];
+        let queens = self.pieces[type_index(PieceType::Queen)];
+        let bishops = self.pieces[type_index(PieceType::Bishop)] | queens;
+        let rooks = self.pieces[type_index(PieceType::Rook)] | queens;
+        ((pawn_attacks(Color::Black, sq) & pawns & self.color(Color::White))
+            | (pawn_attacks(Color::White, sq) & pawns & self.color(Color::Black))
+            | (knight_attacks(sq) & self.pieces[type_index(PieceType::Knight)])
+            | (king_attacks(sq) & self.pieces[type_index(PieceType::King)])
+            | (bishop_attacks(sq, occupied) & bishops)
+            | (rook_attacks(sq, occupied) & rooks))
+            & occupied
+    }
+
+    pub fn is_attacked(&self, sq: Square, by: Color) -> bool {
+        !self.attackers(sq, by).is_empty()
+    }
+
+    pub fn in_check(&self, color: Color) -> bool {
+        match self.pieces(color, PieceType::King).first() {
+            Some(king) => self.is_attacked(king, color.opposite()),
+            None => false,
+        }
+    }
+
+    /// Where the piece on `from` can go without caring about its own king
+    pub(crate) fn destinations(&self, board: &Board, from: Square, piece: Piece) -> Bitboard {
+        let own = self.color(piece.color);
+        match piece.typ {
+            PieceType::Pawn => {
+                let empty = !self.occupied();
+                let (x, y) = Position::from(from);
+                let forward = |steps: usize| match piece.color {
+                    Color::White => Square::new(x, y + steps),
+                    Color::Black => (y >= steps).then(|| Square::new(x, y - steps)).flatten(),
+                };
+                let mut to = Bitboard::EMPTY;
+                if let Some(one) = forward(1).filter(|&sq| empty.contains(sq)) {
+                    to |= one.into();
+                    let start = if piece.color == Color::White { 1 } else { 6 };
+                    if let Some(two) = forward(2).filter(|&sq| y == start && empty.contains(sq)) {
+                        to |= two.into();
+                    }
+                }
+                let enemy = self.color(piece.color.opposite());
+                let mut targets = enemy;
+                // a pawn beside us that has just jumped can be taken on the square it skipped
+                if let (Some((px, py)), Some(one)) = (board.passant_killable, forward(1)) {
+                    if py == y && px.abs_diff(x) == 1 {
+                        let behind = square((px, one.rank()));
+                        if empty.contains(behind) && enemy.contains(square((px, py))) {
+                            targets |= behind.into();
+                        }
+                    }
+                }
+                to | (pawn_attacks(piece.color, from) & targets)
+            }
+            PieceType::King => {
+                let mut to = king_attacks(from) & !own;
+                let pos = Position::from(from);
+                for x in [2, 6] {
+                    if board.can_castle(piece.color, pos, (x, pos.1)) {
+                        to |= square((x, pos.1)).into();
+                    }
+                }
+                to
+            }
+            _ => self.attacks(from, piece) & !own,
+        }
+    }
+
+    /// The pieces after `color` plays `m`, only as far as needed to see whether
+    /// its king is then attacked. A promoted pawn is still counted as a pawn.
+    pub(crate) fn after(&self, m: Move, piece: Piece) -> Self {
+        let mut bb = *self;
+        let (from, to) = (Bitboard::from(square(m.from)), Bitboard::from(square(m.to)));
+        let taken = if m.flags.en_passant {
+            square((m.to.0, m.from.1)).into()
+        } else {
+            to
+        };
+        for set in bb.pieces.iter_mut() {
+            *set &= !taken;
+        }
+        bb.colors[color_index(piece.color.opposite())] &= !taken;
+        bb.pieces[type_index(piece.typ)] ^= from | to;
+        bb.colors[color_index(piece.color)] ^= from | to;
+        if m.flags.castle {
+            let rook_from = if m.to.0 > m.from.0 { 7 } else { 0 };
+            let rook_move = Bitboard::from(square((rook_from, m.from.1)))
+                | square(((m.from.0 + m.to.0) / 2, m.from.1)).into();
+            bb.pieces[type_index(PieceType::Rook)] ^= rook_move;
+            bb.colors[color_index(piece.color)] ^= rook_move;
+        }
+        bb
+    }
+}
+
+impl Board {
+    /// Sets of squares for each kind of piece, for quickly finding attacks
+    pub fn bitboards(&self) -> Bitboards {
+        self.bitboards
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sq(s: &str) -> Square {
+        s.parse().unwrap()
+    }
+
+    fn set(squares: &str) -> Bitboard {
+        squares
+            .split_whitespace()
+            .fold(Bitboard::EMPTY, |bb, s| bb | sq(s).into())
+    }
+
+    #[test]
+    fn bitboard_test() {
+        let bb = set("a1 e4 h8");
+        assert_eq!(bb.count(), 3);
+        assert!(bb.contains(sq("e4")));
+        assert!(!bb.contains(sq("e5")));
+        assert_eq!(bb.first(), Some(sq("a1")));
+        assert_eq!(
+            bb.map(|s| s.to_string()).collect::<Vec<_>>(),
+            ["a1", "e4", "h8"]
+        );
+        assert_eq!(Bitboard::EMPTY.first(), None);
+        assert!((bb & !bb).is_empty());
+        assert_eq!(bb ^ set("e4"), set("a1 h8"));
+    }
+
+    #[test]
+    fn step_attacks_test() {
+        assert_eq!(knight_attacks(sq("a1")), set("b3 c2"));
+        assert_eq!(knight_attacks(sq("e4")).count(), 8);
+        assert_eq!(king_attacks(sq("h8")), set("g8 g7 h7"));
+        assert_eq!(king_attacks(sq("d5")).count(), 8);
+        assert_eq!(pawn_attacks(Color::White, sq("a2")), set("b3"));
+        assert_eq!(pawn_attacks(Color::Black, sq("e5")), set("d4 f4"));
+        assert_eq!(pawn_attacks(Color::White, sq("e8")), Bitboard::EMPTY);
+    }
+
+    #[test]
+    fn slider_attacks_test() {
+        let empty = Bitboard::EMPTY;
+        assert_eq!(rook_attacks(sq("a1"), empty).count(), 14);
+        assert_eq!(bishop_attacks(sq("d4"), empty).count(), 13);
+        assert_eq!(queen_attacks(sq("d4"), empty).count(), 27);
+        // blockers are included, squares behind them are not
+        let occupied = set("d6 b4 f2 d1");
+        assert_eq!(
+            rook_attacks(sq("d4"), occupied),
+            set("d5 d6 c4 b4 e4 f4 g4 h4 d3 d2 d1")
+        );
+        assert_eq!(
+            bishop_attacks(sq("d4"), occupied),
+            set("e5 f6 g7 h8 c5 b6 a7 c3 b2 a1 e3 f2")
+        );
+    }
+
+    #[test]
+    fn attackers_test() {
+        let b = Board::from_fen("4k3/8/2n5/4p3/3K4/8/8/b6R w - - 0 1").unwrap();
+        let bb = b.bitboards();
+        assert_eq!(bb.attackers(sq("d4"), Color::Black), set("c6 e5 a1"));
+        assert_eq!(bb.attackers(sq("e8"), Color::White), Bitboard::EMPTY);
+        assert_eq!(bb.attackers(sq("h8"), Color::White), set("h1"));
+        assert!(bb.in_check(Color::White));
+        assert!(!bb.in_check(Color::Black));
+        assert_eq!(bb.pieces(Color::Black, PieceType::Pawn), set("e5"));
+        assert_eq!(bb.occupied().count(), 6);
+    }
+
+    #[test]
+    fn incremental_bitboards_test() {
+        assert_eq!(Board::default().bitboards, Bitboards::default());
+        assert_eq!(Board::new().bitboards, Bitboards::new(&Board::new()));
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ] {
+            let mut b = Board::from_fen(fen).unwrap();
+            for m in b.legal_moves(b.turn()) {
+                let before = b.bitboards;
+                let undo = b.make_move(m);
+                assert_eq!(b.bitboards, Bitboards::new(&b), "{} {:?}", fen, m);
+                b.unmake_move(m, undo);
+                assert_eq!(b.bitboards, before);
+            }
+        }
+    }
+}
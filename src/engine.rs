@@ -0,0 +1,676 @@
+use crate::eval::{evaluate, piece_value};
+use crate::game::Game;
+use crate::moves::*;
+use crate::ordering::MoveOrdering;
+use crate::pieces::PieceType;
+use crate::tt::{Bound, TranspositionTable};
+use crate::Board;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Score of being checkmated right now, mates further away score a little less
+pub const MATE: i32 = 30_000;
+/// Deepest search iterative deepening will try when no depth is given
+pub const MAX_DEPTH: u32 = 64;
+/// Deepest any line goes, quiescence included, so a mate anywhere in the search
+/// scores within `MAX_PLY` of `MATE`
+pub const MAX_PLY: u32 = 128;
+const INFINITY: i32 = MATE + 1;
+/// How many nodes go by between looks at the clock and the stop flag
+const CHECK_EVERY: u64 = 1024;
+/// Positional gain a capture might bring on top of the piece taken, for delta pruning
+const DELTA_MARGIN: i32 = 200;
+
+/// Is the score a forced mate for one side or the other
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE - MAX_PLY as i32
+}
+
+/// When to stop searching, whichever runs out first. No limits at all searches to `MAX_DEPTH`.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Limits {
+    pub depth: Option<u32>,
+    pub time: Option<Duration>,
+    pub nodes: Option<u64>,
+}
+
+impl Limits {
+    pub fn depth(depth: u32) -> Self {
+        Self {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    pub fn time(time: Duration) -> Self {
+        Self {
+            time: Some(time),
+            ..Default::default()
+        }
+    }
+
+    pub fn nodes(nodes: u64) -> Self {
+        Self {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+}
+
+/// What the deepest finished iteration found
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    /// `None` only when the side to move has no legal moves
+    pub best_move: Option<Move>,
+    /// The line both sides are expected to play, starting with `best_move`
+    pub pv: Vec<Move>,
+    /// Centipawns for the side to move, see `MATE` for mates
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+    pub time: Duration,
+    /// How full the transposition table is, in parts per thousand
+    pub hashfull: u32,
+}
+
+/// Searches positions with negamax alpha-beta, null windows after the first move
+/// and iterative deepening, finishing each line with a quiescence search over captures
+#[derive(Debug, Default)]
+pub struct Engine {
+    stop: Arc<AtomicBool>,
+    limits: Limits,
+    start: Option<Instant>,
+    nodes: u64,
+    stopped: bool,
+    tt: TranspositionTable,
+    ordering: MoveOrdering,
+    /// Hashes of the positions before the one being searched, from the game and
+    /// then the search itself, for spotting repetitions
+    path: Vec<u64>,
+    /// Search moves in the order they are generated, to measure what ordering saves
+    #[cfg(test)]
+    unordered: bool,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An engine whose transposition table takes up about `megabytes` of memory
+    pub fn with_hash_size(megabytes: usize) -> Self {
+        Self {
+            tt: TranspositionTable::new(megabytes),
+            ..Default::default()
+        }
+    }
+
+    /// Resize the transposition table, forgetting what it held
+    pub fn set_hash_size(&mut self, megabytes: usize) {
+        self.tt = TranspositionTable::new(megabytes);
+    }
+
+    /// Forget positions searched before, as at the start of a new game
+    pub fn clear_hash(&mut self) {
+        self.tt.clear();
+    }
+
+    /// How full the transposition table is, in parts per thousand
+    pub fn hashfull(&self) -> u32 {
+        self.tt.hashfull()
+    }
+
+    /// A flag that ends searches early when set, from any thread. It stays set
+    /// until cleared, so a stop sent just before a search starts is not lost.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// Find the best move for the side to move, searching one ply deeper each
+    /// iteration until a limit runs out
+    pub fn search(&mut self, board: &Board, limits: Limits) -> SearchResult {
+        self.search_game(&Game::from_board(*board), limits)
+    }
+
+    /// Like `search`, calling `on_iteration` with the result of each finished iteration
+    pub fn search_with(
+        &mut self,
+        board: &Board,
+        limits: Limits,
+        on_iteration: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        self.search_game_with(&Game::from_board(*board), limits, on_iteration)
+    }
+
+    /// Like `search` for the current position of `game`, treating a return to any
+    /// earlier position of the game as a draw
+    pub fn search_game(&mut self, game: &Game, limits: Limits) -> SearchResult {
+        self.search_game_with(game, limits, |_| {})
+    }
+
+    /// Like `search_game`, calling `on_iteration` with the result of each finished iteration
+    pub fn search_game_with(
+        &mut self,
+        game: &Game,
+        limits: Limits,
+        mut on_iteration: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        let board = game.board();
+        let (_, before) = game
+            .history()
+            .split_last()
+            .expect("a game has at least its starting position");
+        self.path = before.to_vec();
+        self.limits = limits;
+        let start = Instant::now();
+        self.start = Some(start);
+        self.nodes = 0;
+        self.stopped = false;
+        self.tt.new_search();
+        self.ordering.new_search();
+
+        // moves are made and taken back on this one copy as the search goes
+        let mut board = *board;
+        let board = &mut board;
+        let moves = board.legal_moves(board.turn());
+        let mut result = SearchResult {
+            best_move: moves.first().copied(),
+            ..Default::default()
+        };
+        if moves.is_empty() {
+            result.score = self.negamax(board, 1, 0, -INFINITY, INFINITY, &mut Vec::new());
+            return result;
+        }
+
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        for depth in 1..=max_depth {
+            let mut pv = result.pv.clone();
+            let score = self.root(board, &moves, depth, &mut pv);
+            if self.stopped {
+                // a partly searched iteration can miss a refutation, so keep the last full one
+                break;
+            }
+            result.best_move = pv.first().copied();
+            result.pv = pv;
+            result.score = score;
+            result.depth = depth;
+            result.nodes = self.nodes;
+            result.time = start.elapsed();
+            result.hashfull = self.tt.hashfull();
+            on_iteration(&result);
+            if is_mate_score(score) && MATE - score.abs() <= depth as i32 {
+                // nothing deeper can change a mate that is already within reach
+                break;
+            }
+        }
+        result.nodes = self.nodes;
+        result.time = start.elapsed();
+        result.hashfull = self.tt.hashfull();
+        result
+    }
+
+    /// Search every root move, trying the best move of the last iteration first
+    fn root(&mut self, board: &mut Board, moves: &[Move], depth: u32, pv: &mut Vec<Move>) -> i32 {
+        let mut moves = moves.to_vec();
+        self.order(board, &mut moves, pv.first().copied(), 0);
+        self.nodes += 1;
+        let mut alpha = -INFINITY;
+        for (i, m) in moves.into_iter().enumerate() {
+            let mut line = Vec::new();
+            self.path.push(board.hash());
+            let undo = board.make_move(m);
+            let score = if i == 0 {
+                -self.negamax(board, depth - 1, 1, -INFINITY, -alpha, &mut line)
+            } else {
+                self.search_later_move(board, depth - 1, 1, alpha, INFINITY, &mut line)
+            };
+            board.unmake_move(m, undo);
+            self.path.pop();
+            if self.stopped {
+                return 0;
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(m);
+                pv.extend(line);
+            }
+        }
+        let best_move = pv.first().copied();
+        self.tt
+            .store(board.hash(), depth, Bound::Exact, alpha, best_move, 0);
+        alpha
+    }
+
+    fn negamax(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        ply: u32,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> i32 {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECK_EVERY) && self.out_of_budget() {
+            self.stopped = true;
+        }
+        if self.stopped {
+            return 0;
+        }
+
+        // draws come before anything stored for the position, which may have been
+        // reached another way. Only a checkmate outranks the fifty move rule.
+        if board.halfmove_clock() >= 100 {
+            let mated = board.in_check(board.turn()) && !board.has_legal_move(board.turn());
+            return if mated { -MATE + ply as i32 } else { 0 };
+        }
+        if board.insufficient_material() || self.is_repetition(board) {
+            return 0;
+        }
+
+        let hash = board.hash();
+        let entry = self.tt.probe(hash, ply);
+        // a null window search only needs the score, but inside a wider window an
+        // exact score would become part of the PV, which the table cannot give back
+        let pv_node = beta - alpha > 1;
+        if let Some(entry) = entry {
+            let usable = entry.depth >= depth
+                && match entry.bound {
+                    Bound::Exact => !pv_node || entry.score <= alpha || entry.score >= beta,
+                    Bound::Lower => entry.score >= beta,
+                    Bound::Upper => entry.score <= alpha,
+                };
+            if usable {
+                return entry.score;
+            }
+        }
+        if depth == 0 {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        let mut moves = board.legal_moves(board.turn());
+        if moves.is_empty() {
+            return if board.in_check(board.turn()) {
+                -MATE + ply as i32
+            } else {
+                0
+            };
+        }
+        self.order(board, &mut moves, entry.and_then(|e| e.best_move), ply);
+        let original_alpha = alpha;
+        let mut best_move = None;
+        for (i, m) in moves.into_iter().enumerate() {
+            let mut line = Vec::new();
+            self.path.push(hash);
+            let undo = board.make_move(m);
+            let score = if i == 0 {
+                -self.negamax(board, depth - 1, ply + 1, -beta, -alpha, &mut line)
+            } else {
+                self.search_later_move(board, depth - 1, ply + 1, alpha, beta, &mut line)
+            };
+            board.unmake_move(m, undo);
+            self.path.pop();
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                self.ordering.cutoff(board.turn(), m, ply, depth);
+                self.tt
+                    .store(hash, depth, Bound::Lower, score, Some(m), ply);
+                return score;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(m);
+                pv.clear();
+                pv.push(m);
+                pv.extend(line);
+            }
+        }
+        let bound = if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt.store(hash, depth, bound, alpha, best_move, ply);
+        alpha
+    }
+
+    /// Score the position after any move but the first, from the side that made it.
+    /// Such moves are expected to be worse than the first, which a null window around
+    /// `alpha` proves more cheaply. Only when that turns out wrong is the move
+    /// searched again with the full window.
+    fn search_later_move(
+        &mut self,
+        board: &mut Board,
+        depth: u32,
+        ply: u32,
+        alpha: i32,
+        beta: i32,
+        line: &mut Vec<Move>,
+    ) -> i32 {
+        let score = -self.negamax(board, depth, ply, -alpha - 1, -alpha, line);
+        if score <= alpha || score >= beta || self.stopped {
+            return score;
+        }
+        line.clear();
+        -self.negamax(board, depth, ply, -beta, -alpha, line)
+    }
+
+    /// Play out captures and promotions until the position is quiet, so the search
+    /// does not stop in the middle of an exchange. The side to move may always
+    /// stand pat on the static evaluation instead, unless it is in check.
+    fn quiescence(&mut self, board: &mut Board, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(CHECK_EVERY) && self.out_of_budget() {
+            self.stopped = true;
+        }
+        if self.stopped {
+            return 0;
+        }
+        if ply >= MAX_PLY {
+            return evaluate(board);
+        }
+
+        let in_check = board.in_check(board.turn());
+        let (mut moves, stand_pat) = if in_check {
+            let moves = board.legal_moves(board.turn());
+            if moves.is_empty() {
+                return -MATE + ply as i32;
+            }
+            (moves, None)
+        } else {
+            let stand_pat = evaluate(board);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+            (board.captures(board.turn()), Some(stand_pat))
+        };
+        // captures are always sorted, even unordered, or the exchanges would never end
+        self.ordering.order(board, &mut moves, None, ply);
+
+        for m in moves {
+            if let (Some(stand_pat), None) = (stand_pat, m.promotion) {
+                let victim = if m.flags.en_passant {
+                    Some(PieceType::Pawn)
+                } else {
+                    board.piece_at(m.to).map(|p| p.typ)
+                };
+                // even winning the piece for nothing would not be enough
+                if stand_pat + victim.map_or(0, piece_value) + DELTA_MARGIN <= alpha {
+                    continue;
+                }
+                if board.see(m) < 0 {
+                    continue;
+                }
+            }
+            let undo = board.make_move(m);
+            let score = -self.quiescence(board, ply + 1, -beta, -alpha);
+            board.unmake_move(m, undo);
+            if self.stopped {
+                return 0;
+            }
+            if score >= beta {
+                return score;
+            }
+            alpha = alpha.max(score);
+        }
+        alpha
+    }
+
+    /// Has the position been seen before with the same side to move, since the
+    /// last capture or pawn move. Once is enough, a side that could repeat it
+    /// can repeat it again.
+    fn is_repetition(&self, board: &Board) -> bool {
+        self.path
+            .iter()
+            .rev()
+            .take(board.halfmove_clock() as usize)
+            .skip(1)
+            .step_by(2)
+            .any(|&h| h == board.hash())
+    }
+
+    fn order(&self, board: &Board, moves: &mut [Move], hash_move: Option<Move>, ply: u32) {
+        #[cfg(test)]
+        if self.unordered {
+            if let Some(i) = hash_move.and_then(|best| moves.iter().position(|&m| m == best)) {
+                // the root still needs the best move so far first, to keep it when time runs out
+                moves[..=i].rotate_right(1);
+            }
+            return;
+        }
+        self.ordering.order(board, moves, hash_move, ply);
+    }
+
+    fn out_of_budget(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+            || self.limits.nodes.is_some_and(|n| self.nodes >= n)
+            || self
+                .limits
+                .time
+                .zip(self.start)
+                .is_some_and(|(time, start)| start.elapsed() >= time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uci(board: &Board, s: &str) -> Move {
+        board.parse_uci(s).unwrap()
+    }
+
+    #[test]
+    fn mate_in_one_test() {
+        let b =
+            Board::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 4 4")
+                .unwrap();
+        let result = Engine::new().search(&b, Limits::depth(3));
+        assert_eq!(result.best_move, Some(uci(&b, "f3f7")));
+        assert_eq!(result.score, MATE - 1);
+        assert!(is_mate_score(result.score));
+        // the search stops once the mate is found
+        assert_eq!(result.depth, 1);
+    }
+
+    #[test]
+    fn mate_in_two_test() {
+        // a rook ladder, Ra7 and then Rb8 mates
+        let b = Board::from_fen("6k1/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let result = Engine::new().search(&b, Limits::depth(4));
+        assert_eq!(result.score, MATE - 3);
+        assert_eq!(result.pv.len(), 3);
+        let mut after = b;
+        for &m in &result.pv {
+            after.play(m).unwrap();
+        }
+        assert!(after.in_check(after.turn()));
+        assert!(!after.has_legal_move(after.turn()));
+
+        // and from the losing side
+        let b = Board::from_fen("6k1/R7/8/8/8/8/8/1R4K1 b - - 1 1").unwrap();
+        let result = Engine::new().search(&b, Limits::depth(3));
+        assert_eq!(result.score, -(MATE - 2));
+    }
+
+    #[test]
+    fn material_test() {
+        // the queen can be taken for free
+        let b = Board::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let result = Engine::new().search(&b, Limits::depth(2));
+        assert_eq!(result.best_move, Some(uci(&b, "d2d5")));
+        assert_eq!(result.pv[0], uci(&b, "d2d5"));
+        assert!(result.score > 400);
+
+        // taking this pawn loses the rook to the other pawn
+        let b = Board::from_fen("4k3/8/2p5/3p4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let result = Engine::new().search(&b, Limits::depth(2));
+        assert_ne!(result.best_move, Some(uci(&b, "d2d5")));
+    }
+
+    #[test]
+    fn limits_test() {
+        let b = Board::new();
+        let result = Engine::new().search(&b, Limits::depth(2));
+        assert_eq!(result.depth, 2);
+        assert_eq!(result.pv.len(), 2);
+        assert!(b.is_legal(result.best_move.unwrap()));
+
+        // the node budget is only looked at every so often
+        let result = Engine::new().search(&b, Limits::nodes(1));
+        assert!(result.depth < MAX_DEPTH);
+        assert!(b.is_legal(result.best_move.unwrap()));
+        assert!(result.nodes <= CHECK_EVERY);
+
+        let start = Instant::now();
+        let result = Engine::new().search(&b, Limits::time(Duration::from_millis(50)));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(result.best_move.is_some());
+
+        let mut engine = Engine::new();
+        let stop = engine.stop_flag();
+        stop.store(true, Ordering::Relaxed);
+        let result = engine.search(&b, Limits::depth(10));
+        assert!(result.depth < 10);
+        assert!(result.nodes <= CHECK_EVERY);
+        stop.store(false, Ordering::Relaxed);
+        let mut depths = Vec::new();
+        let result = engine.search_with(&b, Limits::depth(3), |r| depths.push(r.depth));
+        assert_eq!(depths, [1, 2, 3]);
+        assert_eq!(result.depth, 3);
+    }
+
+    #[test]
+    fn hash_test() {
+        // plenty of ways to reach the same king and pawn positions
+        let b = Board::from_fen("4k3/8/3p4/8/8/3P4/8/4K3 w - - 0 1").unwrap();
+        let without = Engine::with_hash_size(0).search(&b, Limits::depth(6));
+        assert_eq!(without.hashfull, 0);
+        let mut engine = Engine::with_hash_size(1);
+        let with = engine.search(&b, Limits::depth(6));
+        assert!(with.nodes < without.nodes);
+        assert!(with.hashfull > 0);
+        assert!(b.is_legal(with.best_move.unwrap()));
+
+        // searching again starts out knowing the answers
+        let again = engine.search(&b, Limits::depth(6));
+        assert!(again.nodes < with.nodes);
+
+        // the table never cuts the line short, even when searching the same position again
+        let b = Board::new();
+        let mut engine = Engine::with_hash_size(1);
+        for _ in 0..2 {
+            let mut lines = Vec::new();
+            engine.search_with(&b, Limits::depth(4), |r| lines.push(r.pv.len()));
+            assert_eq!(lines, [1, 2, 3, 4]);
+        }
+
+        // mates found through the table are still counted from the root
+        let b = Board::from_fen("6k1/8/8/8/8/8/R7/1R4K1 w - - 0 1").unwrap();
+        let mut engine = Engine::with_hash_size(1);
+        assert_eq!(engine.search(&b, Limits::depth(4)).score, MATE - 3);
+        assert_eq!(engine.search(&b, Limits::depth(4)).score, MATE - 3);
+    }
+
+    #[test]
+    fn ordering_test() {
+        let search = |fen, unordered| {
+            let mut engine = Engine {
+                unordered,
+                ..Engine::with_hash_size(0)
+            };
+            engine.search(&Board::from_fen(fen).unwrap(), Limits::depth(2))
+        };
+        for fen in [
+            // a quiet Italian opening, where killers and history do the work
+            "r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/3P1N2/PPP2PPP/RNBQK2R w KQkq - 1 5",
+            // Kiwipete, full of captures to sort
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        ] {
+            let (ordered, unordered) = (search(fen, false), search(fen, true));
+            assert_eq!(ordered.score, unordered.score);
+            assert!(ordered.nodes * 3 < unordered.nodes * 2, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn quiescence_test() {
+        // at depth one only the exchanges that follow show the pawn is defended
+        let b = Board::from_fen("4k3/8/2p5/3p4/8/8/3Q4/4K3 w - - 0 1").unwrap();
+        let result = Engine::new().search(&b, Limits::depth(1));
+        assert_ne!(result.best_move, Some(uci(&b, "d2d5")));
+
+        // a quiet position is worth its static evaluation
+        let mut b = Board::new();
+        let mut engine = Engine::new();
+        assert_eq!(
+            engine.quiescence(&mut b, 0, -INFINITY, INFINITY),
+            evaluate(&b)
+        );
+        // the hanging queen is as good as taken, and the pawn the queen defends is left alone
+        let mut b = Board::from_fen("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let score = engine.quiescence(&mut b, 0, -INFINITY, INFINITY);
+        assert!(score > evaluate(&b) + 500);
+        let mut b = Board::from_fen("4k3/8/8/3q4/8/8/3p4/3R3K w - - 0 1").unwrap();
+        let score = engine.quiescence(&mut b, 0, -INFINITY, INFINITY);
+        assert!(score < evaluate(&b) + 100);
+        // in check there is no standing pat, and no way out is mate
+        let mut b = Board::from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        assert!(engine.quiescence(&mut b, 0, -INFINITY, INFINITY) < 0);
+        let mut b = Board::from_fen("4k3/8/8/8/8/8/r7/r3K3 w - - 0 1").unwrap();
+        assert_eq!(
+            engine.quiescence(&mut b, 2, -INFINITY, INFINITY),
+            -(MATE - 2)
+        );
+        // the deepest mate a line can reach still counts as one
+        let score = engine.quiescence(&mut b, MAX_PLY - 1, -INFINITY, INFINITY);
+        assert!(is_mate_score(score));
+        // and no line goes deeper
+        assert_eq!(
+            engine.quiescence(&mut b, MAX_PLY, -INFINITY, INFINITY),
+            evaluate(&b)
+        );
+    }
+
+    #[test]
+    fn repetition_test() {
+        // a queen down, white can only hope to repeat the position
+        let start = Board::from_fen("7k/q7/8/8/8/8/8/6NK w - - 0 1").unwrap();
+        let mut game = Game::from_board(start);
+        for m in ["g1f3", "a7a6", "f3g1", "a6a7"] {
+            let m = uci(game.board(), m);
+            game.play(m).unwrap();
+        }
+        let result = Engine::new().search(game.board(), Limits::depth(2));
+        assert!(result.score < -500);
+        let result = Engine::new().search_game(&game, Limits::depth(2));
+        assert_eq!(result.best_move, Some(uci(&start, "g1f3")));
+        assert_eq!(result.score, 0);
+    }
+
+    #[test]
+    fn game_over_test() {
+        let mated =
+            Board::from_fen("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+                .unwrap();
+        let result = Engine::new().search(&mated, Limits::depth(3));
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, -MATE);
+
+        let stalemate = Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        let result = Engine::new().search(&stalemate, Limits::depth(3));
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, 0);
+
+        // a mate on the move that reaches the fifty move limit still counts
+        let b = Board::from_fen("7k/R7/6K1/8/8/8/8/8 w - - 99 80").unwrap();
+        let result = Engine::new().search(&b, Limits::depth(2));
+        assert_eq!(result.best_move, Some(uci(&b, "a7a8")));
+        assert_eq!(result.score, MATE - 1);
+    }
+}
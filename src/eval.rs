@@ -0,0 +1,509 @@
+use crate::bitboard::square;
+use crate::pieces::*;
+use crate::{Board, Position};
+use std::ops::{Add, AddAssign, Mul, Sub};
+
+/// Game phase of the starting position, counting knights and bishops as 1, rooks as 2 and queens as 4
+pub const MAX_PHASE: i32 = 24;
+
+/// A score in centipawns for the middlegame and for the endgame, blended by `taper`
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const fn new(mg: i32, eg: i32) -> Self {
+        Self { mg, eg }
+    }
+}
+
+impl Add for Score {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, other: Self) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Score {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Self;
+
+    fn mul(self, n: i32) -> Self {
+        Self::new(self.mg * n, self.eg * n)
+    }
+}
+
+/// What a piece is worth in centipawns, the king being priceless counts as nothing
+pub fn piece_value(typ: PieceType) -> i32 {
+    match typ {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 320,
+        PieceType::Bishop => 330,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::King => 0,
+    }
+}
+
+// Piece-square tables from white's side, laid out as the board is drawn with a8 first.
+// Only pawns and kings change their minds about good squares in the endgame.
+#[rustfmt::skip]
+const PAWN_MG: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    50, 50, 50, 50, 50, 50, 50, 50,
+    10, 10, 20, 30, 30, 20, 10, 10,
+     5,  5, 10, 25, 25, 10,  5,  5,
+     0,  0,  0, 20, 20,  0,  0,  0,
+     5, -5,-10,  0,  0,-10, -5,  5,
+     5, 10, 10,-20,-20, 10, 10,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+#[rustfmt::skip]
+const PAWN_EG: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+    60, 60, 60, 60, 60, 60, 60, 60,
+    40, 40, 40, 40, 40, 40, 40, 40,
+    25, 25, 25, 25, 25, 25, 25, 25,
+    15, 15, 15, 15, 15, 15, 15, 15,
+     5,  5,  5,  5,  5,  5,  5,  5,
+     0,  0,  0,  0,  0,  0,  0,  0,
+     0,  0,  0,  0,  0,  0,  0,  0,
+];
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+    -50,-40,-30,-30,-30,-30,-40,-50,
+    -40,-20,  0,  0,  0,  0,-20,-40,
+    -30,  0, 10, 15, 15, 10,  0,-30,
+    -30,  5, 15, 20, 20, 15,  5,-30,
+    -30,  0, 15, 20, 20, 15,  0,-30,
+    -30,  5, 10, 15, 15, 10,  5,-30,
+    -40,-20,  0,  5,  5,  0,-20,-40,
+    -50,-40,-30,-30,-30,-30,-40,-50,
+];
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+    -20,-10,-10,-10,-10,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5, 10, 10,  5,  0,-10,
+    -10,  5,  5, 10, 10,  5,  5,-10,
+    -10,  0, 10, 10, 10, 10,  0,-10,
+    -10, 10, 10, 10, 10, 10, 10,-10,
+    -10,  5,  0,  0,  0,  0,  5,-10,
+    -20,-10,-10,-10,-10,-10,-10,-20,
+];
+#[rustfmt::skip]
+const ROOK: [i32; 64] = [
+     0,  0,  0,  0,  0,  0,  0,  0,
+     5, 10, 10, 10, 10, 10, 10,  5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+    -5,  0,  0,  0,  0,  0,  0, -5,
+     0,  0,  0,  5,  5,  0,  0,  0,
+];
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+    -20,-10,-10, -5, -5,-10,-10,-20,
+    -10,  0,  0,  0,  0,  0,  0,-10,
+    -10,  0,  5,  5,  5,  5,  0,-10,
+     -5,  0,  5,  5,  5,  5,  0, -5,
+      0,  0,  5,  5,  5,  5,  0, -5,
+    -10,  5,  5,  5,  5,  5,  0,-10,
+    -10,  0,  5,  0,  0,  0,  0,-10,
+    -20,-10,-10, -5, -5,-10,-10,-20,
+];
+#[rustfmt::skip]
+const KING_MG: [i32; 64] = [
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -30,-40,-40,-50,-50,-40,-40,-30,
+    -20,-30,-30,-40,-40,-30,-30,-20,
+    -10,-20,-20,-20,-20,-20,-20,-10,
+     20, 20,  0,  0,  0,  0, 20, 20,
+     20, 30, 10,  0,  0, 10, 30, 20,
+];
+#[rustfmt::skip]
+const KING_EG: [i32; 64] = [
+    -50,-40,-30,-20,-20,-30,-40,-50,
+    -30,-20,-10,  0,  0,-10,-20,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 30, 40, 40, 30,-10,-30,
+    -30,-10, 20, 30, 30, 20,-10,-30,
+    -30,-30,  0,  0,  0,  0,-30,-30,
+    -50,-30,-30,-30,-30,-30,-30,-50,
+];
+
+const DOUBLED_PAWN: Score = Score::new(-10, -20);
+const ISOLATED_PAWN: Score = Score::new(-15, -20);
+/// Bonus for a passed pawn by how many ranks it has advanced
+const PASSED_PAWN: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(5, 10),
+    Score::new(10, 20),
+    Score::new(15, 35),
+    Score::new(25, 60),
+    Score::new(40, 100),
+    Score::new(60, 150),
+    Score::new(0, 0),
+];
+/// Pawns one and two ranks in front of the king
+const PAWN_SHIELD: [i32; 2] = [10, 5];
+/// A file next to or under the king without a pawn of its own
+const OPEN_KING_FILE: i32 = -15;
+
+/// How many ranks up the board a square is for `color`, 0 being its back rank
+fn relative_rank(color: Color, y: usize) -> usize {
+    match color {
+        Color::White => y,
+        Color::Black => 7 - y,
+    }
+}
+
+/// The same square seen from white's side, so black can read the tables too
+fn table_index(color: Color, (x, y): Position) -> usize {
+    (7 - relative_rank(color, y)) * 8 + x
+}
+
+/// White's share minus black's share of some term
+fn white_minus_black(term: impl Fn(Color) -> Score) -> Score {
+    term(Color::White) - term(Color::Black)
+}
+
+impl Board {
+    fn pieces(&self) -> impl Iterator<Item = (Position, Piece)> + '_ {
+        (0..8).flat_map(move |y| (0..8).filter_map(move |x| Some(((x, y), self.board[y][x]?))))
+    }
+
+    fn pawn_files(&self, color: Color) -> [i32; 8] {
+        let mut files = [0; 8];
+        for (pos, p) in self.pieces() {
+            if p == Piece::new(color, PieceType::Pawn) {
+                files[pos.0] += 1;
+            }
+        }
+        files
+    }
+}
+
+/// How far the game is from the endgame, from `MAX_PHASE` with all pieces on down to 0
+pub fn phase(board: &Board) -> i32 {
+    let phase = board
+        .pieces()
+        .map(|(_, p)| match p.typ {
+            PieceType::Knight | PieceType::Bishop => 1,
+            PieceType::Rook => 2,
+            PieceType::Queen => 4,
+            PieceType::Pawn | PieceType::King => 0,
+        })
+        .sum::<i32>();
+    phase.min(MAX_PHASE)
+}
+
+/// Blend the middlegame and endgame halves of a score by the game phase
+pub fn taper(score: Score, phase: i32) -> i32 {
+    (score.mg * phase + score.eg * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+/// Material balance from white's side
+pub fn material(board: &Board) -> Score {
+    white_minus_black(|color| {
+        let value = board
+            .pieces()
+            .filter(|(_, p)| p.color == color)
+            .map(|(_, p)| piece_value(p.typ))
+            .sum();
+        Score::new(value, value)
+    })
+}
+
+/// How well placed each piece is on its own, from white's side
+pub fn piece_squares(board: &Board) -> Score {
+    white_minus_black(|color| {
+        let mut score = Score::default();
+        for (pos, p) in board.pieces().filter(|(_, p)| p.color == color) {
+            let i = table_index(color, pos);
+            score += match p.typ {
+                PieceType::Pawn => Score::new(PAWN_MG[i], PAWN_EG[i]),
+                PieceType::Knight => Score::new(KNIGHT[i], KNIGHT[i]),
+                PieceType::Bishop => Score::new(BISHOP[i], BISHOP[i]),
+                PieceType::Rook => Score::new(ROOK[i], ROOK[i]),
+                PieceType::Queen => Score::new(QUEEN[i], QUEEN[i]),
+                PieceType::King => Score::new(KING_MG[i], KING_EG[i]),
+            };
+        }
+        score
+    })
+}
+
+/// Doubled and isolated pawns count against a side, passed pawns for it, from white's side
+pub fn pawn_structure(board: &Board) -> Score {
+    white_minus_black(|color| {
+        let own = board.pawn_files(color);
+        let mut score = Score::default();
+        for &n in own.iter().filter(|&&n| n > 1) {
+            score += DOUBLED_PAWN * (n - 1);
+        }
+        let enemy_pawn = Piece::new(color.opposite(), PieceType::Pawn);
+        for ((x, y), _) in board
+            .pieces()
+            .filter(|&(_, p)| p == Piece::new(color, PieceType::Pawn))
+        {
+            let files = x.saturating_sub(1)..=(x + 1).min(7);
+            if files.clone().all(|f| f == x || own[f] == 0) {
+                score += ISOLATED_PAWN;
+            }
+            let rank = relative_rank(color, y);
+            // passed when no enemy pawn stands in front of it on its own file or either side
+            let blocked = files.clone().any(|f| {
+                (0..8).any(|ey| {
+                    relative_rank(color, ey) > rank && board.board[ey][f] == Some(enemy_pawn)
+                })
+            });
+            if !blocked {
+                score += PASSED_PAWN[rank];
+            }
+        }
+        score
+    })
+}
+
+/// Pawns sheltering the king, less any open files around it, from white's side.
+/// Only counts in the middlegame, when there are pieces left to attack with.
+pub fn king_safety(board: &Board) -> Score {
+    white_minus_black(|color| {
+        let Some((kx, ky)) = board.find_king(color) else {
+            return Score::default();
+        };
+        let pawn = Some(Piece::new(color, PieceType::Pawn));
+        let own = board.pawn_files(color);
+        let mut safety = 0;
+        let files = kx.saturating_sub(1)..=(kx + 1).min(7);
+        for (x, &pawns) in own
+            .iter()
+            .enumerate()
+            .take(files.end() + 1)
+            .skip(*files.start())
+        {
+            for (step, bonus) in PAWN_SHIELD.iter().enumerate() {
+                let rank = relative_rank(color, ky) + step + 1;
+                if rank < 8 && board.board[relative_rank(color, rank)][x] == pawn {
+                    safety += bonus;
+                }
+            }
+            if pawns == 0 {
+                safety += OPEN_KING_FILE;
+            }
+        }
+        Score::new(safety, 0)
+    })
+}
+
+/// How many squares the knights, bishops, rooks and queens can move to, from white's side
+pub fn mobility(board: &Board) -> Score {
+    white_minus_black(|color| {
+        let mut score = Score::default();
+        let bb = board.bitboards();
+        for (pos, p) in board.pieces().filter(|(_, p)| p.color == color) {
+            let weight = match p.typ {
+                PieceType::Knight => Score::new(4, 4),
+                PieceType::Bishop => Score::new(5, 5),
+                PieceType::Rook => Score::new(2, 4),
+                PieceType::Queen => Score::new(1, 2),
+                PieceType::Pawn | PieceType::King => continue,
+            };
+            let from = square(pos);
+            let count = (bb.attacks(from, p) & !bb.color(color)).count();
+            score += weight * count as i32;
+        }
+        score
+    })
+}
+
+/// Score the position in centipawns for the side to move
+pub fn evaluate(board: &Board) -> i32 {
+    let score = material(board)
+        + piece_squares(board)
+        + pawn_structure(board)
+        + king_safety(board)
+        + mobility(board);
+    let score = taper(score, phase(board));
+    match board.turn() {
+        Color::White => score,
+        Color::Black => -score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The same position with the colors swapped and the board turned upside down
+    fn mirror(fen: &str) -> Board {
+        let fields = fen.split_whitespace().collect::<Vec<_>>();
+        let swap = |s: &str| {
+            s.chars()
+                .map(|c| {
+                    if c.is_ascii_uppercase() {
+                        c.to_ascii_lowercase()
+                    } else {
+                        c.to_ascii_uppercase()
+                    }
+                })
+                .collect::<String>()
+        };
+        let placement = fields[0].split('/').rev().map(swap).collect::<Vec<_>>();
+        let turn = if fields[1] == "w" { "b" } else { "w" };
+        let passant = fields[3]
+            .chars()
+            .map(|c| match c {
+                '3' => '6',
+                '6' => '3',
+                c => c,
+            })
+            .collect::<String>();
+        Board::from_fen(&format!(
+            "{} {} {} {}",
+            placement.join("/"),
+            turn,
+            swap(fields[2]),
+            passant
+        ))
+        .unwrap()
+    }
+
+    const POSITIONS: [&str; 4] = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "6k1/5ppp/8/3P4/8/8/1p3PPP/6K1 b - - 0 30",
+    ];
+
+    #[test]
+    fn symmetry_test() {
+        let b = Board::new();
+        assert_eq!(evaluate(&b), 0);
+        for term in [
+            material,
+            piece_squares,
+            pawn_structure,
+            king_safety,
+            mobility,
+        ] {
+            assert_eq!(term(&b), Score::default());
+        }
+        for fen in POSITIONS {
+            let (b, m) = (Board::from_fen(fen).unwrap(), mirror(fen));
+            assert_eq!(evaluate(&b), evaluate(&m), "{}", fen);
+            for term in [
+                material,
+                piece_squares,
+                pawn_structure,
+                king_safety,
+                mobility,
+            ] {
+                assert_eq!(term(&b), Score::default() - term(&m), "{}", fen);
+            }
+        }
+    }
+
+    #[test]
+    fn material_test() {
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(material(&b), Score::new(900, 900));
+        let b = Board::from_fen("4k3/pp6/8/8/8/8/8/1N2K3 b - - 0 1").unwrap();
+        assert_eq!(material(&b), Score::new(120, 120));
+        // the side to move sees things its own way
+        let score = evaluate(&b);
+        assert!(score < 0);
+        assert_eq!(evaluate(&mirror(&b.to_fen())), score);
+    }
+
+    #[test]
+    fn phase_test() {
+        assert_eq!(phase(&Board::new()), MAX_PHASE);
+        let b = Board::from_fen("4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1").unwrap();
+        assert_eq!(phase(&b), 0);
+        let b = Board::from_fen("r3k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(phase(&b), 6);
+
+        let score = Score::new(100, -20);
+        assert_eq!(taper(score, MAX_PHASE), 100);
+        assert_eq!(taper(score, 0), -20);
+        assert_eq!(taper(score, MAX_PHASE / 2), 40);
+    }
+
+    #[test]
+    fn piece_square_test() {
+        // knights belong in the center
+        let center = Board::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        let corner = Board::from_fen("4k3/8/8/8/8/8/8/N3K3 w - - 0 1").unwrap();
+        assert!(piece_squares(&center).mg > piece_squares(&corner).mg);
+        // kings hide in the middlegame and come out in the endgame
+        let castled = Board::from_fen("4k3/8/8/8/8/8/8/6K1 w - - 0 1").unwrap();
+        let central = Board::from_fen("4k3/8/8/8/4K3/8/8/8 w - - 0 1").unwrap();
+        assert!(piece_squares(&castled).mg > piece_squares(&central).mg);
+        assert!(piece_squares(&castled).eg < piece_squares(&central).eg);
+    }
+
+    #[test]
+    fn pawn_structure_test() {
+        // a doubled pair on the c file, each isolated
+        let b = Board::from_fen("4k3/8/8/8/8/2P5/2P5/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            pawn_structure(&b),
+            DOUBLED_PAWN + ISOLATED_PAWN * 2 + PASSED_PAWN[1] + PASSED_PAWN[2]
+        );
+        // neither white pawn is passed with the lone black pawn covering both files
+        let b = Board::from_fen("4k3/8/3p4/8/8/8/2PP4/4K3 w - - 0 1").unwrap();
+        assert_eq!(pawn_structure(&b), Score::default() - ISOLATED_PAWN);
+        // a far advanced passer
+        let b = Board::from_fen("4k3/1P6/8/8/8/p7/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(pawn_structure(&b), PASSED_PAWN[6] - PASSED_PAWN[5]);
+    }
+
+    #[test]
+    fn king_safety_test() {
+        let sheltered = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1").unwrap();
+        assert_eq!(king_safety(&sheltered), Score::default());
+        let exposed = Board::from_fen("6k1/5ppp/8/8/8/5P2/6PP/6K1 w - - 0 1").unwrap();
+        assert_eq!(
+            king_safety(&exposed),
+            Score::new(PAWN_SHIELD[1] - PAWN_SHIELD[0], 0)
+        );
+        let open = Board::from_fen("6k1/5ppp/8/8/8/8/5P1P/6K1 w - - 0 1").unwrap();
+        assert_eq!(
+            king_safety(&open),
+            Score::new(OPEN_KING_FILE - PAWN_SHIELD[0], 0)
+        );
+    }
+
+    #[test]
+    fn mobility_test() {
+        let b = Board::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(mobility(&b), Score::new(32, 32));
+        let b = Board::from_fen("4k3/8/8/8/8/8/1P6/N3K3 w - - 0 1").unwrap();
+        assert_eq!(mobility(&b), Score::new(8, 8));
+        // hemmed in by its own pawn and king
+        let b = Board::from_fen("4k3/8/8/8/8/8/PP6/R3K3 w - - 0 1").unwrap();
+        assert_eq!(mobility(&b), Score::new(3 * 2, 3 * 4));
+    }
+}
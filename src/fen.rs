@@ -0,0 +1,289 @@
+use crate::pieces::*;
+use crate::square::Square;
+use crate::{Board, CastleRights, Position};
+use std::{error, fmt};
+
+/// The standard starting position
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    /// A FEN needs either four fields or all six including the clocks
+    FieldCount(usize),
+    RankCount(usize),
+    /// A rank, counted from 8 down to 1 as written, does not add up to eight squares
+    RankLength(usize),
+    InvalidPiece(char),
+    InvalidTurn(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use FenError::*;
+        match self {
+            FieldCount(n) => write!(f, "expected 4 or 6 space separated fields, found {}", n),
+            RankCount(n) => write!(f, "expected 8 ranks separated by '/', found {}", n),
+            RankLength(rank) => write!(f, "rank {} does not describe exactly 8 squares", rank),
+            InvalidPiece(c) => write!(f, "'{}' is not a piece letter", c),
+            InvalidTurn(s) => write!(f, "side to move must be 'w' or 'b', found '{}'", s),
+            InvalidCastling(s) => write!(f, "invalid castling rights '{}'", s),
+            InvalidEnPassant(s) => write!(f, "invalid en passant target '{}'", s),
+            InvalidHalfmoveClock(s) => write!(f, "invalid halfmove clock '{}'", s),
+            InvalidFullmoveNumber(s) => write!(f, "invalid fullmove number '{}'", s),
+        }
+    }
+}
+
+impl error::Error for FenError {}
+
+impl Board {
+    /// Set up a board from Forsyth-Edwards Notation. The two clocks may be left off.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields = fen.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 4 && fields.len() != 6 {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+        let mut board = Board {
+            castle_rights: CastleRights {
+                white_left: false,
+                white_right: false,
+                black_left: false,
+                black_right: false,
+            },
+            ..Default::default()
+        };
+
+        let ranks = fields[0].split('/').collect::<Vec<_>>();
+        if ranks.len() != 8 {
+            return Err(FenError::RankCount(ranks.len()));
+        }
+        // ranks are written from black's side of the board down to white's
+        for (i, rank) in ranks.into_iter().enumerate() {
+            let y = 7 - i;
+            let mut x = 0;
+            for c in rank.chars() {
+                if let Some(n) = c.to_digit(10).filter(|n| (1..=8).contains(n)) {
+                    x += n as usize;
+                } else {
+                    let typ = PieceType::from_letter(c.to_ascii_uppercase())
+                        .ok_or(FenError::InvalidPiece(c))?;
+                    let color = if c.is_ascii_uppercase() {
+                        Color::White
+                    } else {
+                        Color::Black
+                    };
+                    if x < 8 {
+                        board.board[y][x] = Some(Piece::new(color, typ));
+                    }
+                    x += 1;
+                }
+                if x > 8 {
+                    return Err(FenError::RankLength(y + 1));
+                }
+            }
+            if x != 8 {
+                return Err(FenError::RankLength(y + 1));
+            }
+        }
+
+        board.turn = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            s => return Err(FenError::InvalidTurn(s.to_string())),
+        };
+
+        if fields[2] != "-" {
+            let rights = &mut board.castle_rights;
+            for c in fields[2].chars() {
+                let right = match c {
+                    'K' => &mut rights.white_right,
+                    'Q' => &mut rights.white_left,
+                    'k' => &mut rights.black_right,
+                    'q' => &mut rights.black_left,
+                    _ => return Err(FenError::InvalidCastling(fields[2].to_string())),
+                };
+                if *right {
+                    // each right may only be given once
+                    return Err(FenError::InvalidCastling(fields[2].to_string()));
+                }
+                *right = true;
+            }
+        }
+
+        if fields[3] != "-" {
+            let err = || FenError::InvalidEnPassant(fields[3].to_string());
+            let target = Position::from(fields[3].parse::<Square>().map_err(|_| err())?);
+            // the pawn that can be taken is one step past the square it skipped over
+            let (pawn, color) = match (target.1, board.turn) {
+                (2, Color::Black) => ((target.0, 3), Color::White),
+                (5, Color::White) => ((target.0, 4), Color::Black),
+                _ => return Err(err()),
+            };
+            if board.board[pawn.1][pawn.0] != Some(Piece::new(color, PieceType::Pawn)) {
+                return Err(err());
+            }
+            board.passant_killable = Some(pawn);
+        }
+
+        if fields.len() == 6 {
+            board.halfmove_clock = fields[4]
+                .parse()
+                .map_err(|_| FenError::InvalidHalfmoveClock(fields[4].to_string()))?;
+            board.fullmove_number = fields[5]
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| FenError::InvalidFullmoveNumber(fields[5].to_string()))?;
+        }
+        board.sync_from_squares();
+        Ok(board)
+    }
+
+    /// Describe the board in Forsyth-Edwards Notation
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for y in (0..8).rev() {
+            let mut empty = 0;
+            for x in 0..8 {
+                match self.board[y][x] {
+                    Some(p) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(match p.color {
+                            Color::White => p.typ.letter(),
+                            Color::Black => p.typ.letter().to_ascii_lowercase(),
+                        });
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if y > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match self.turn {
+            Color::White => " w ",
+            Color::Black => " b ",
+        });
+
+        let rights = self.castle_rights;
+        let castling = [
+            (rights.white_right, 'K'),
+            (rights.white_left, 'Q'),
+            (rights.black_right, 'k'),
+            (rights.black_left, 'q'),
+        ]
+        .into_iter()
+        .filter_map(|(allowed, c)| allowed.then_some(c))
+        .collect::<String>();
+        fen.push_str(if castling.is_empty() { "-" } else { &castling });
+
+        fen.push(' ');
+        match self.passant_killable.and_then(|(x, y)| {
+            // the target is the square the pawn skipped over
+            Square::new(x, if y == 3 { 2 } else { 5 })
+        }) {
+            Some(target) => fen.push_str(&target.to_string()),
+            None => fen.push('-'),
+        }
+
+        fen.push_str(&format!(
+            " {} {}",
+            self.halfmove_clock, self.fullmove_number
+        ));
+        fen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::Move;
+
+    #[test]
+    fn start_fen_test() {
+        assert_eq!(Board::from_fen(START_FEN), Ok(Board::new()));
+        assert_eq!(Board::new().to_fen(), START_FEN);
+        // the clocks are optional
+        assert_eq!(
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"),
+            Ok(Board::new())
+        );
+    }
+
+    #[test]
+    fn round_trip_test() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 40",
+        ] {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn fen_matches_play_test() {
+        let mut b = Board::new();
+        b.play(Move::new((4, 1), (4, 3))).unwrap();
+        assert_eq!(
+            b.to_fen(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+        b.play(Move::new((6, 7), (5, 5))).unwrap();
+        b.play(Move::new((4, 0), (4, 1))).unwrap();
+        assert_eq!(
+            b.to_fen(),
+            "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPPKPPP/RNBQ1BNR b kq - 2 2"
+        );
+        assert_eq!(Board::from_fen(&b.to_fen()), Ok(b));
+    }
+
+    #[test]
+    fn fen_error_test() {
+        use FenError::*;
+        for (fen, err) in [
+            ("8/8/8/8/8/8/8/8 w", FieldCount(2)),
+            ("8/8/8/8/8/8/8/8 w - - 0", FieldCount(5)),
+            ("8/8/8/8/8/8/8 w - - 0 1", RankCount(7)),
+            ("8/8/8/8/8/8/8/7 w - - 0 1", RankLength(1)),
+            ("8/8/8/8/8/8/8/ppppppppp w - - 0 1", RankLength(1)),
+            ("44/8/8/8/8/8/8/54 w - - 0 1", RankLength(1)),
+            ("8/8/8/8/8/8/8/9 w - - 0 1", InvalidPiece('9')),
+            ("8/8/8/8/8/8/8/4x3 w - - 0 1", InvalidPiece('x')),
+            ("8/8/8/8/8/8/8/8 white - - 0 1", InvalidTurn("white".into())),
+            ("8/8/8/8/8/8/8/8 w KK - 0 1", InvalidCastling("KK".into())),
+            ("8/8/8/8/8/8/8/8 w Kx - 0 1", InvalidCastling("Kx".into())),
+            ("8/8/8/8/8/8/8/8 w - e9 0 1", InvalidEnPassant("e9".into())),
+            ("8/8/8/8/8/8/8/8 w - e3 0 1", InvalidEnPassant("e3".into())),
+            ("8/8/8/8/8/8/8/8 b - e3 0 1", InvalidEnPassant("e3".into())),
+            (
+                "8/8/8/8/8/8/8/8 w - - x 1",
+                InvalidHalfmoveClock("x".into()),
+            ),
+            (
+                "8/8/8/8/8/8/8/8 w - - 0 0",
+                InvalidFullmoveNumber("0".into()),
+            ),
+        ] {
+            assert_eq!(Board::from_fen(fen), Err(err), "{}", fen);
+        }
+        assert_eq!(
+            RankLength(3).to_string(),
+            "rank 3 does not describe exactly 8 squares"
+        );
+    }
+}
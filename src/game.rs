@@ -0,0 +1,133 @@
+use crate::moves::*;
+use crate::status::*;
+use crate::Board;
+
+/// A board along with every position it has been through, for detecting repetitions
+#[derive(Clone)]
+pub struct Game {
+    board: Board,
+    /// The hash of each position so far, see `Board::hash`
+    history: Vec<u64>,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Game {
+    pub fn new() -> Self {
+        Self::from_board(Board::new())
+    }
+
+    /// Start a game from an arbitrary position
+    pub fn from_board(board: Board) -> Self {
+        Self {
+            board,
+            history: vec![board.hash()],
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// The hash of every position so far, oldest first and ending with the current one
+    pub fn history(&self) -> &[u64] {
+        &self.history
+    }
+
+    /// Play `m` for the side to move and remember the position it leads to
+    pub fn play(&mut self, m: Move) -> Result<Move, MoveError> {
+        let m = self.board.play(m)?;
+        self.history.push(self.board.hash());
+        Ok(m)
+    }
+
+    /// How many times the current position has occurred, including now
+    pub fn repetitions(&self) -> usize {
+        let hash = self.board.hash();
+        // a capture or pawn move can never be undone so nothing before it can repeat
+        let reversible = self.board.halfmove_clock as usize + 1;
+        self.history
+            .iter()
+            .rev()
+            .take(reversible)
+            .filter(|&&h| h == hash)
+            .count()
+    }
+
+    /// Like `Board::status` but also aware of repeated positions
+    pub fn status(&self) -> GameStatus {
+        match self.board.status() {
+            GameStatus::InProgress => match self.repetitions() {
+                5.. => GameStatus::Draw(DrawReason::FivefoldRepetition),
+                3.. => GameStatus::Draw(DrawReason::ThreefoldRepetition),
+                _ => GameStatus::InProgress,
+            },
+            status => status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repetition_test() {
+        let mut g = Game::new();
+        let shuffle = [
+            Move::new((6, 0), (5, 2)),
+            Move::new((6, 7), (5, 5)),
+            Move::new((5, 2), (6, 0)),
+            Move::new((5, 5), (6, 7)),
+        ];
+        assert_eq!(g.repetitions(), 1);
+        for m in shuffle {
+            g.play(m).unwrap();
+            assert_eq!(g.status(), GameStatus::InProgress);
+        }
+        assert_eq!(g.repetitions(), 2);
+        for m in shuffle {
+            g.play(m).unwrap();
+        }
+        assert_eq!(g.repetitions(), 3);
+        assert_eq!(
+            g.status(),
+            GameStatus::Draw(DrawReason::ThreefoldRepetition)
+        );
+        for m in shuffle.iter().chain(&shuffle) {
+            g.play(*m).unwrap();
+        }
+        assert_eq!(g.repetitions(), 5);
+        assert_eq!(g.status(), GameStatus::Draw(DrawReason::FivefoldRepetition));
+    }
+
+    #[test]
+    fn repetition_needs_same_rights_test() {
+        let mut g = Game::new();
+        g.play(Move::new((4, 1), (4, 3))).unwrap();
+        g.play(Move::new((4, 6), (4, 4))).unwrap();
+        // the kings walk out and back, losing the right to castle
+        let walk = [
+            Move::new((4, 0), (4, 1)),
+            Move::new((4, 7), (4, 6)),
+            Move::new((4, 1), (4, 0)),
+            Move::new((4, 6), (4, 7)),
+        ];
+        for m in walk.iter().chain(&walk) {
+            g.play(*m).unwrap();
+        }
+        assert_eq!(g.repetitions(), 2);
+        assert_eq!(g.status(), GameStatus::InProgress);
+        for m in walk {
+            g.play(m).unwrap();
+        }
+        assert_eq!(
+            g.status(),
+            GameStatus::Draw(DrawReason::ThreefoldRepetition)
+        );
+    }
+}
@@ -0,0 +1,1432 @@
+pub mod bitboard;
+pub mod engine;
+pub mod eval;
+pub mod fen;
+pub mod game;
+#[cfg(any(test, feature = "bench"))]
+mod mailbox;
+pub mod moves;
+pub mod ordering;
+pub mod perft;
+pub mod pgn;
+pub mod pieces;
+pub mod render;
+pub mod san;
+pub mod see;
+pub mod square;
+pub mod status;
+pub mod tt;
+pub mod uci;
+pub mod zobrist;
+use bitboard::Bitboards;
+use moves::*;
+use pieces::*;
+
+pub type Position = (usize, usize);
+
+/// Which rooks each side may still castle with, left being the queen side rook on the a file
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CastleRights {
+    pub white_left: bool,
+    pub white_right: bool,
+    pub black_left: bool,
+    pub black_right: bool,
+}
+
+impl Default for CastleRights {
+    fn default() -> Self {
+        Self {
+            white_left: true,
+            white_right: true,
+            black_left: true,
+            black_right: true,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Board {
+    /// The piece on each square, indexed by rank and then file. A view of the
+    /// same position as `bitboards` that is quicker for asking about one square.
+    board: [[Option<Piece>; 8]; 8],
+    passant_killable: Option<Position>,
+    castle_rights: CastleRights,
+    turn: Color,
+    /// Plies since the last capture or pawn move
+    halfmove_clock: u32,
+    /// Starts at 1 and goes up after each move by black
+    fullmove_number: u32,
+    /// Zobrist hash kept up to date by `make_move`, see `hash`
+    hash: u64,
+    /// Sets of squares for each piece type and color, kept up to date by `make_move`
+    bitboards: Bitboards,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        let mut board = Self {
+            board: [[None; 8]; 8],
+            passant_killable: None,
+            castle_rights: CastleRights::default(),
+            turn: Color::White,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            hash: 0,
+            bitboards: Bitboards::default(),
+        };
+        board.hash = board.compute_hash();
+        board
+    }
+}
+
+impl Board {
+    pub fn new() -> Self {
+        Self::from_squares([
+            [
+                Some(Piece::new(Color::White, PieceType::Rook)),
+                Some(Piece::new(Color::White, PieceType::Knight)),
+                Some(Piece::new(Color::White, PieceType::Bishop)),
+                Some(Piece::new(Color::White, PieceType::Queen)),
+                Some(Piece::new(Color::White, PieceType::King)),
+                Some(Piece::new(Color::White, PieceType::Bishop)),
+                Some(Piece::new(Color::White, PieceType::Knight)),
+                Some(Piece::new(Color::White, PieceType::Rook)),
+            ],
+            [Some(Piece::new(Color::White, PieceType::Pawn)); 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [Some(Piece::new(Color::Black, PieceType::Pawn)); 8],
+            [
+                Some(Piece::new(Color::Black, PieceType::Rook)),
+                Some(Piece::new(Color::Black, PieceType::Knight)),
+                Some(Piece::new(Color::Black, PieceType::Bishop)),
+                Some(Piece::new(Color::Black, PieceType::Queen)),
+                Some(Piece::new(Color::Black, PieceType::King)),
+                Some(Piece::new(Color::Black, PieceType::Bishop)),
+                Some(Piece::new(Color::Black, PieceType::Knight)),
+                Some(Piece::new(Color::Black, PieceType::Rook)),
+            ],
+        ])
+    }
+
+    /// A board with `board` as its squares and everything else as at the start of a game
+    fn from_squares(board: [[Option<Piece>; 8]; 8]) -> Self {
+        let mut board = Self {
+            board,
+            ..Default::default()
+        };
+        board.sync_from_squares();
+        board
+    }
+
+    /// Work out the hash and bitboards from the squares, once a board has been filled in
+    fn sync_from_squares(&mut self) {
+        self.hash = self.compute_hash();
+        self.bitboards = Bitboards::new(self);
+    }
+
+    pub fn castle_rights(&self) -> CastleRights {
+        self.castle_rights
+    }
+
+    /// The color whose turn it is to move
+    pub fn turn(&self) -> Color {
+        self.turn
+    }
+
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// The piece on `pos`, or `None` if the square is empty or off the board
+    pub fn piece_at(&self, pos: impl Into<Position>) -> Option<Piece> {
+        let pos = pos.into();
+        *self.board.get(pos.1)?.get(pos.0)?
+    }
+
+    fn test_move(&self, m: Move) -> Self {
+        let mut board = *self;
+        board.make_move(m);
+        board
+    }
+
+    /// Apply `m` to the board without checking that it is legal, see `is_legal`.
+    /// Returns what is needed to take the move back with `unmake_move`.
+    pub fn make_move(&mut self, m: Move) -> Undo {
+        let (a, b) = (m.from, m.to);
+        let mut undo = Undo {
+            moved: None,
+            captured: None,
+            rook: None,
+            castle_rights: self.castle_rights,
+            passant_killable: self.passant_killable,
+            turn: self.turn,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash,
+        };
+        let p = self.set_square(a, None);
+        undo.moved = p;
+        let pawn = matches!(
+            p,
+            Some(Piece {
+                typ: PieceType::Pawn,
+                ..
+            })
+        );
+        if pawn && a.0 != b.0 && self.board[b.1][b.0].is_none() {
+            // en passant, the pawn being taken is beside us rather than where we land
+            undo.captured = self.set_square((b.0, a.1), None).map(|p| ((b.0, a.1), p));
+        }
+        // a pawn can only be taken en passant right after its two square jump
+        self.passant_killable = (pawn && a.1.abs_diff(b.1) == 2).then_some(b);
+        if pawn || self.board[b.1][b.0].is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if let Some(p) = p {
+            if p.color == Color::Black {
+                self.fullmove_number += 1;
+            }
+            self.turn = p.color.opposite();
+        }
+        if matches!(
+            p,
+            Some(Piece {
+                typ: PieceType::King,
+                ..
+            })
+        ) && a.0.abs_diff(b.0) == 2
+        {
+            // castling, the rook jumps to the other side of the king
+            let from = (if b.0 > a.0 { 7 } else { 0 }, a.1);
+            let to = ((a.0 + b.0) / 2, a.1);
+            let rook = self.set_square(from, None);
+            self.set_square(to, rook);
+            undo.rook = Some((from, to));
+        }
+        self.revoke_castle_rights(a);
+        self.revoke_castle_rights(b);
+        let taken = self.set_square(
+            b,
+            match m.promotion {
+                Some(typ) => p.map(|p| Piece::new(p.color, typ)),
+                None => p,
+            },
+        );
+        if let Some(taken) = taken {
+            undo.captured = Some((b, taken));
+        }
+        self.hash ^= zobrist::castle_key(undo.castle_rights)
+            ^ zobrist::castle_key(self.castle_rights)
+            ^ zobrist::passant_key(undo.passant_killable)
+            ^ zobrist::passant_key(self.passant_killable)
+            ^ zobrist::turn_key(undo.turn)
+            ^ zobrist::turn_key(self.turn);
+        undo
+    }
+
+    /// Take back `m`, which must be the last move made, restoring the board exactly
+    pub fn unmake_move(&mut self, m: Move, undo: Undo) {
+        self.set_square(m.to, None);
+        self.set_square(m.from, undo.moved);
+        if let Some((pos, p)) = undo.captured {
+            self.set_square(pos, Some(p));
+        }
+        if let Some((from, to)) = undo.rook {
+            let rook = self.set_square(to, None);
+            self.set_square(from, rook);
+        }
+        self.castle_rights = undo.castle_rights;
+        self.passant_killable = undo.passant_killable;
+        self.turn = undo.turn;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.hash = undo.hash;
+    }
+
+    /// Put `piece` on `pos`, keeping the hash and bitboards up to date, and return whatever was there
+    fn set_square(&mut self, pos: Position, piece: Option<Piece>) -> Option<Piece> {
+        let old = std::mem::replace(&mut self.board[pos.1][pos.0], piece);
+        for p in old.iter().chain(&piece) {
+            self.hash ^= zobrist::piece_key(*p, pos);
+            self.bitboards.toggle(bitboard::square(pos), *p);
+        }
+        old
+    }
+
+    /// Remove any castling rights that depend on a king or rook standing on `pos`.
+    /// Called for both ends of a move so that moving a piece and capturing one are covered.
+    fn revoke_castle_rights(&mut self, pos: Position) {
+        let rights = &mut self.castle_rights;
+        match pos {
+            (4, 0) => {
+                rights.white_left = false;
+                rights.white_right = false;
+            }
+            (0, 0) => rights.white_left = false,
+            (7, 0) => rights.white_right = false,
+            (4, 7) => {
+                rights.black_left = false;
+                rights.black_right = false;
+            }
+            (0, 7) => rights.black_left = false,
+            (7, 7) => rights.black_right = false,
+            _ => {}
+        }
+    }
+
+    /// Check if the king of `color` on `a` is allowed to castle by moving to `b`.
+    /// The king may not castle out of or through check, moving into check is left to the caller.
+    fn can_castle(&self, color: Color, a: Position, b: Position) -> bool {
+        let rank = if color == Color::White { 0 } else { 7 };
+        if a != (4, rank) || b.1 != rank {
+            return false;
+        }
+        let rights = self.castle_rights;
+        let (allowed, rook, between) = match (color, b.0) {
+            (Color::White, 2) => (rights.white_left, 0, 1..4),
+            (Color::White, 6) => (rights.white_right, 7, 5..7),
+            (Color::Black, 2) => (rights.black_left, 0, 1..4),
+            (Color::Black, 6) => (rights.black_right, 7, 5..7),
+            _ => return false,
+        };
+        allowed
+            && self.board[rank][rook] == Some(Piece::new(color, PieceType::Rook))
+            && self.board[rank][between].iter().all(|x| x.is_none())
+            && !self.in_check(color)
+            && !self.leaves_king_in_check(Move::new(a, ((a.0 + b.0) / 2, rank)))
+    }
+
+    fn find_king(&self, color: Color) -> Option<Position> {
+        (0..8)
+            .flat_map(|y| (0..8).map(move |x| (x, y)))
+            .find(|&(x, y)| {
+                matches!(
+                    self.board[y][x],
+                    Some(Piece { color: c, typ: PieceType::King, .. }) if color == c
+                )
+            })
+    }
+
+    pub fn in_check(&self, color: Color) -> bool {
+        self.bitboards().in_check(color)
+    }
+
+    /// Check if playing `m` would leave the mover's own king attacked, without
+    /// making the move on a copy of the board
+    fn leaves_king_in_check(&self, m: Move) -> bool {
+        match self.piece_at(m.from) {
+            Some(p) => {
+                let m = Move {
+                    flags: self.move_flags(m.from, m.to),
+                    ..m
+                };
+                self.bitboards().after(m, p).in_check(p.color)
+            }
+            None => false,
+        }
+    }
+
+    /// Play `m` for the side to move, returning it with its flags filled in
+    pub fn play(&mut self, m: Move) -> Result<Move, MoveError> {
+        let m = self.check_move(m)?;
+        self.make_move(m);
+        Ok(m)
+    }
+
+    /// Check that the side to move can play `m`, returning it with its flags filled in
+    pub fn check_move(&self, m: Move) -> Result<Move, MoveError> {
+        let p = self.piece_at(m.from).ok_or(MoveError::NoPiece)?;
+        if p.color != self.turn {
+            return Err(MoveError::WrongColor);
+        }
+        if !self.valid_pattern(m.from, m.to) {
+            return Err(MoveError::IllegalMove);
+        }
+        if self.leaves_king_in_check(m) {
+            return Err(MoveError::LeavesKingInCheck);
+        }
+        self.check_promotion(m)?;
+        Ok(Move {
+            flags: self.move_flags(m.from, m.to),
+            ..m
+        })
+    }
+
+    /// Check that `m` is a valid move which promotes exactly when a pawn reaches the last rank
+    pub fn is_legal(&self, m: Move) -> bool {
+        self.valid_move(m.from, m.to) && self.check_promotion(m).is_ok()
+    }
+
+    fn check_promotion(&self, m: Move) -> Result<(), MoveError> {
+        let promotes = matches!(
+            self.board[m.from.1][m.from.0],
+            Some(Piece {
+                typ: PieceType::Pawn,
+                ..
+            })
+        ) && (m.to.1 == 0 || m.to.1 == 7);
+        match m.promotion {
+            None if promotes => Err(MoveError::MissingPromotion),
+            Some(typ) if !promotes || !PROMOTIONS.contains(&typ) => {
+                Err(MoveError::InvalidPromotion)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Every legal move `color` can make in this position
+    pub fn legal_moves(&self, color: Color) -> Vec<Move> {
+        self.generate_moves(color, false)
+    }
+
+    /// The legal captures, en passant included, and promotions `color` can make,
+    /// without generating the quiet moves at all
+    pub fn captures(&self, color: Color) -> Vec<Move> {
+        self.generate_moves(color, true)
+    }
+
+    fn generate_moves(&self, color: Color, only_captures: bool) -> Vec<Move> {
+        /// Where pawns promote, for either color
+        const LAST_RANKS: bitboard::Bitboard = bitboard::Bitboard(0xff00_0000_0000_00ff);
+        let bb = self.bitboards();
+        let mut moves = Vec::new();
+        for from in bb.color(color) {
+            let piece = self
+                .piece_at(from)
+                .expect("the square is in the color's set");
+            let mut destinations = bb.destinations(self, from, piece);
+            if only_captures {
+                // a pawn's diagonal steps always take something, en passant if the square is empty
+                destinations &= match piece.typ {
+                    PieceType::Pawn => bitboard::pawn_attacks(color, from) | LAST_RANKS,
+                    _ => bb.color(color.opposite()),
+                };
+            }
+            for to in destinations {
+                let (a, b) = (Position::from(from), Position::from(to));
+                let m = Move {
+                    flags: self.move_flags(a, b),
+                    ..Move::new(a, b)
+                };
+                if bb.after(m, piece).in_check(color) {
+                    continue;
+                }
+                if piece.typ == PieceType::Pawn && (b.1 == 0 || b.1 == 7) {
+                    moves.extend(PROMOTIONS.map(|typ| Move {
+                        promotion: Some(typ),
+                        ..m
+                    }));
+                } else {
+                    moves.push(m);
+                }
+            }
+        }
+        moves
+    }
+
+    /// Work out what moving the piece on `a` to `b` does, assuming the move is valid
+    fn move_flags(&self, a: Position, b: Position) -> MoveFlags {
+        let en_passant = matches!(
+            self.board[a.1][a.0],
+            Some(Piece {
+                typ: PieceType::Pawn,
+                ..
+            })
+        ) && a.0 != b.0
+            && self.board[b.1][b.0].is_none();
+        let castle = matches!(
+            self.board[a.1][a.0],
+            Some(Piece {
+                typ: PieceType::King,
+                ..
+            })
+        ) && a.0.abs_diff(b.0) == 2;
+        MoveFlags {
+            capture: en_passant || self.board[b.1][b.0].is_some(),
+            en_passant,
+            castle,
+        }
+    }
+
+    /// Given a list of relative positions check if the move to be checked is one of those positions
+    fn rel_posns(self, list: &[Position], a: Position, b: Position) -> bool {
+        list.iter()
+            .any(|&x| x == (a.0.abs_diff(b.0), a.1.abs_diff(b.1)))
+    }
+
+    /// Check if the piece on `a` can legally move to `b`
+    pub fn valid_move(&self, a: impl Into<Position>, b: impl Into<Position>) -> bool {
+        let (a, b) = (a.into(), b.into());
+        self.valid_pattern(a, b) && !self.leaves_king_in_check(Move::new(a, b))
+    }
+
+    /// Check if the piece on `a` moves in a way that can reach `b`,
+    /// without caring whether the move leaves its own king in check
+    fn valid_pattern(&self, a: Position, b: Position) -> bool {
+        macro_rules! check {
+            (Pawn_step: $p:expr) => {
+                if $p.color == Color::Black {
+                    a.1 == b.1 + 1
+                } else {
+                    a.1 + 1 == b.1
+                }
+            };
+            (Pawn_y: $p:expr) => {
+                check!(Pawn_step: $p)
+                    // check for two spot jump
+                    || if $p.color == Color::Black {
+                        a.1 == 6 && b.1 == 4 && self.board[5][a.0].is_none()
+                    } else {
+                        a.1 == 1 && b.1 == 3 && self.board[2][a.0].is_none()
+                    }
+            };
+            (Rook) => {
+                if a.0 == b.0 {
+                    let range = if a.1 > b.1 {
+                        b.1 + 1..a.1
+                    } else {
+                        a.1 + 1..b.1
+                    };
+                    self.board[range]
+                        .iter()
+                        .all(|x| x[a.0].is_none())
+                } else if a.1 == b.1 {
+                    let range = if a.0 > b.0 {
+                        b.0 + 1..a.0
+                    } else {
+                        a.0 + 1..b.0
+                    };
+                    self.board[a.1][range]
+                        .iter()
+                        .all(|x| x.is_none())
+                } else {
+                    false
+                }
+            };
+            (Bishop) => {
+                if a.0.abs_diff(b.0) == a.1.abs_diff(b.1) {
+                    // walk from a towards b, which may be in any of the four diagonal directions
+                    let x = |i| if b.0 > a.0 { a.0 + i } else { a.0 - i };
+                    let y = |i| if b.1 > a.1 { a.1 + i } else { a.1 - i };
+                    (1..a.0.abs_diff(b.0)).all(|i| self.board[y(i)][x(i)].is_none())
+                } else {
+                    false
+                }
+            };
+            (Queen) => {
+                check!(Rook) || check!(Bishop)
+            };
+        }
+        if [a.0, a.1, b.0, b.1].into_iter().any(|x| x >= 8) || a == b {
+            return false;
+        }
+        if let Some(p1) = self.board[a.1][a.0] {
+            if let Some(p2) = self.board[b.1][b.0] {
+                if p1.color == p2.color {
+                    return false;
+                }
+                if p1.typ == PieceType::Pawn {
+                    // captures only ever take one step forward
+                    return a.0.abs_diff(b.0) == 1 && check!(Pawn_step: p1);
+                }
+            }
+            match p1.typ {
+                PieceType::Pawn => {
+                    (a.0 == b.0 && check!(Pawn_y: p1))
+                        || (self.passant_killable == Some((b.0, a.1))
+                            && matches!(
+                                self.board[a.1][b.0],
+                                Some(Piece { color: c, .. }) if c != p1.color
+                            )
+                            && a.0.abs_diff(b.0) == 1
+                            && check!(Pawn_step: p1))
+                }
+                PieceType::Rook => check!(Rook),
+                PieceType::Knight => self.rel_posns(&[(1, 2), (2, 1)], a, b),
+                PieceType::Bishop => check!(Bishop),
+                PieceType::Queen => check!(Queen),
+                PieceType::King => {
+                    self.rel_posns(&[(1, 1), (1, 0), (0, 1)], a, b)
+                        || self.can_castle(p1.color, a, b)
+                }
+            }
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pawn_move_test() {
+        let b = Board::new();
+        for x in 0..8 {
+            assert!(b.valid_move((x, 1), (x, 2)));
+            assert!(b.valid_move((x, 1), (x, 3)));
+            assert!(b.valid_move((x, 6), (x, 5)));
+            assert!(b.valid_move((x, 6), (x, 4)));
+        }
+        let b = Board::from_squares([
+            [Some(Piece::new(Color::White, PieceType::Pawn)); 8],
+            [Some(Piece::new(Color::Black, PieceType::Pawn)); 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+        ]);
+        for x in 0..8 {
+            assert!(!b.valid_move((x, 0), (x, 1))); // move white to black
+            assert!(!b.valid_move((x, 1), (x, 0))); // move black to white
+        }
+        for x in 0..7 {
+            assert!(b.valid_move((x, 0), (x + 1, 1))); // move white to black Diagonally
+            assert!(b.valid_move((x, 1), (x + 1, 0))); // move black to white Diagonally
+            assert!(b.valid_move((x + 1, 0), (x, 1))); // move white to black Diagonally
+            assert!(b.valid_move((x + 1, 1), (x, 0))); // move black to white Diagonally
+        }
+        let mut b = Board::from_squares([
+            [None; 8],
+            [
+                Some(Piece::new(Color::White, PieceType::Pawn)),
+                Some(Piece::new(Color::Black, PieceType::Pawn)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+        ]);
+        b.passant_killable = Some((0, 1));
+        b.sync_from_squares();
+        assert!(b.valid_move((1, 1), (1, 0)));
+        assert!(b.valid_move((1, 1), (0, 0)));
+        assert!(!b.valid_move((1, 1), (2, 0)));
+    }
+
+    #[test]
+    fn pawn_capture_distance_test() {
+        // captures never jump two squares, even from the starting rank
+        let b = Board::from_fen("4k3/8/8/8/1p6/8/P7/4K3 w - - 0 1").unwrap();
+        assert!(!b.valid_move((0, 1), (1, 3)));
+        let b = Board::from_fen("4k3/6p1/8/7P/8/8/8/4K3 b - - 0 1").unwrap();
+        assert!(!b.valid_move((6, 6), (7, 4)));
+    }
+
+    #[test]
+    fn rook_move_test() {
+        let b = Board::new();
+        assert!(!b.valid_move((0, 0), (0, 3))); // Try to move thru piece on same team
+        assert!(!b.valid_move((0, 0), (0, 7))); // Try to move thru pieces of multiple colors
+        assert!(!b.valid_move((0, 0), (3, 0))); // Try to move thru pieces on same team
+        assert!(!b.valid_move((7, 0), (7, 3))); // Try to move thru piece
+        assert!(!b.valid_move((7, 0), (7, 7))); // Try to move thru pieces of multiple colors
+        assert!(!b.valid_move((7, 0), (3, 0))); // Try to move thru pieces on same team
+        let b = Board::from_squares([
+            [Some(Piece::new(Color::White, PieceType::Rook)); 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [Some(Piece::new(Color::Black, PieceType::Rook)); 8],
+        ]);
+        // vertical movement
+        for x in 0..8 {
+            assert!(b.valid_move((x, 0), (x, 7))); // white takes black
+            assert!(b.valid_move((x, 0), (x, 4))); // white moves without take
+            assert!(b.valid_move((x, 7), (x, 0))); // black takes white
+            assert!(b.valid_move((x, 7), (x, 4))); // black moves without take
+        }
+        let b = Board::from_squares(
+            [[
+                Some(Piece::new(Color::White, PieceType::Rook)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(Piece::new(Color::Black, PieceType::Rook)),
+            ]; 8],
+        );
+        // horizontal movement
+        for y in 0..8 {
+            assert!(b.valid_move((0, y), (7, y))); // white takes black
+            assert!(b.valid_move((0, y), (4, y))); // white moves without take
+            assert!(b.valid_move((7, y), (0, y))); // black takes white
+            assert!(b.valid_move((7, y), (4, y))); // black moves without take
+        }
+    }
+
+    #[test]
+    fn knight_move_test() {
+        let b = Board::new();
+        assert!(b.valid_move(
+            (1, 7), // Black left knight
+            (2, 5), // Up two right one
+        ));
+        assert!(b.valid_move(
+            (1, 7), // Black left knight
+            (0, 5), // Up two left one
+        ));
+        assert!(b.valid_move(
+            (6, 7), // Black right knight
+            (7, 5), // Up two right one
+        ));
+        assert!(b.valid_move(
+            (6, 7), // Black right knight
+            (5, 5), // Up two left one
+        ));
+        assert!(b.valid_move(
+            (1, 0), // White left knight
+            (2, 2), // Down two right one
+        ));
+        assert!(b.valid_move(
+            (1, 0), // White left knight
+            (0, 2), // Down two left one
+        ));
+        assert!(b.valid_move(
+            (6, 0), // White right knight
+            (7, 2), // Down two right one
+        ));
+        assert!(b.valid_move(
+            (6, 0), // White right knight
+            (5, 2), // Down two left one
+        ));
+        assert!(!b.valid_move(
+            (1, 0), // White right knight
+            (3, 0), // Down two left one
+        ));
+        let b = Board::from_squares([
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [
+                None,
+                None,
+                None,
+                None,
+                Some(Piece::new(Color::Black, PieceType::Knight)),
+                None,
+                None,
+                None,
+            ],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+        ]);
+        for pos in [
+            (5, 6),
+            (3, 6),
+            (5, 2),
+            (3, 2),
+            (6, 5),
+            (6, 3),
+            (2, 5),
+            (2, 3),
+        ] {
+            assert!(b.valid_move(
+                (4, 4), // Knight in the center
+                pos
+            ));
+        }
+    }
+
+    #[test]
+    fn bishop_move_test() {
+        let b = Board::new();
+        assert!(!b.valid_move((0, 0), (7, 7)));
+        assert!(!b.valid_move((0, 0), (4, 4)));
+        let b = Board::from_squares([
+            [Some(Piece::new(Color::White, PieceType::Bishop)); 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [Some(Piece::new(Color::Black, PieceType::Bishop)); 8],
+        ]);
+        assert!(b.valid_move((0, 0), (7, 7))); // white takes black
+        assert!(b.valid_move((7, 0), (0, 7))); // white takes black
+        assert!(b.valid_move((7, 7), (0, 0))); // black takes white
+        assert!(b.valid_move((0, 7), (7, 0))); // black takes white
+        assert!(b.valid_move((0, 0), (4, 4))); // white moves without taking
+        assert!(b.valid_move((7, 7), (4, 4))); // black moves without taking
+        assert!(b.valid_move((1, 0), (7, 6))); // white moves without taking
+        assert!(b.valid_move((1, 0), (4, 3))); // white moves without taking
+    }
+
+    #[test]
+    fn bishop_diagonal_test() {
+        // bishops are blocked along all four diagonals, not just up and to the right
+        let mut b = Board::new();
+        b.set_square((3, 1), None);
+        b.set_square((4, 6), None);
+        assert!(b.valid_move((5, 7), (1, 3))); // black moves thru the gap
+        assert!(!b.valid_move((5, 0), (1, 4))); // white is blocked by its own pawn
+        b.set_square((4, 1), None);
+        assert!(b.valid_move((5, 0), (1, 4)));
+        assert!(b.valid_move((2, 0), (7, 5)));
+        assert!(!b.valid_move((2, 0), (0, 2))); // blocked in the other direction
+    }
+
+    #[test]
+    fn queen_move_test() {
+        let b = Board::new();
+        // moving vertically thru peices
+        assert!(!b.valid_move((3, 0), (3, 7)));
+        assert!(!b.valid_move((3, 7), (3, 0)));
+        // moving diagonally thru peices
+        assert!(!b.valid_move((3, 0), (7, 4)));
+        assert!(!b.valid_move((3, 0), (0, 3)));
+        // move horizontally thru peices
+        assert!(!b.valid_move((3, 0), (3, 3))); // try to move thru piece on same team
+        assert!(!b.valid_move((3, 0), (3, 7))); // try to move thru pieces of multiple colors
+        assert!(!b.valid_move((3, 0), (0, 0))); // try to move thru pieces on same team
+        let b = Board::from_squares([
+            [Some(Piece::new(Color::White, PieceType::Queen)); 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [Some(Piece::new(Color::Black, PieceType::Queen)); 8],
+        ]);
+        // vertical moves
+        for x in 0..8 {
+            assert!(b.valid_move((x, 0), (x, 7))); // white takes black
+            assert!(b.valid_move((x, 0), (x, 4))); // white moves without take
+            assert!(b.valid_move((x, 7), (x, 0))); // black takes white
+            assert!(b.valid_move((x, 7), (x, 4))); // black moves without take
+        }
+        // diagonal moves
+        assert!(b.valid_move((0, 0), (7, 7))); // white takes black
+        assert!(b.valid_move((7, 0), (0, 7))); // white takes black
+        assert!(b.valid_move((7, 7), (0, 0))); // black takes white
+        assert!(b.valid_move((0, 7), (7, 0))); // black takes white
+        assert!(b.valid_move((0, 0), (4, 4))); // white moves without taking
+        assert!(b.valid_move((7, 7), (4, 4))); // black moves without taking
+        assert!(b.valid_move((1, 0), (7, 6))); // white moves without taking
+        assert!(b.valid_move((1, 0), (4, 3))); // white moves without taking
+        let b = Board::from_squares(
+            [[
+                Some(Piece::new(Color::White, PieceType::Queen)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(Piece::new(Color::Black, PieceType::Queen)),
+            ]; 8],
+        );
+        // Horizontal moves
+        for y in 0..8 {
+            assert!(b.valid_move((0, y), (7, y))); // white takes black
+            assert!(b.valid_move((0, y), (4, y))); // white moves without take
+            assert!(b.valid_move((7, y), (0, y))); // black takes white
+            assert!(b.valid_move((7, y), (4, y))); // black moves without take
+        }
+    }
+
+    #[test]
+    fn king_move_test() {
+        let b = Board::new();
+        for pos in [(3, 0), (3, 1), (4, 1), (5, 1), (5, 0)] {
+            assert!(!b.valid_move(
+                (4, 0), // White King
+                pos
+            ));
+        }
+        for pos in [(3, 7), (3, 6), (4, 6), (5, 6), (5, 7)] {
+            assert!(!b.valid_move(
+                (4, 7), // Black King
+                pos
+            ));
+        }
+        let b = Board::from_squares([
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [
+                None,
+                None,
+                None,
+                None,
+                Some(Piece::new(Color::Black, PieceType::King)),
+                None,
+                None,
+                None,
+            ],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+        ]);
+        for pos in [
+            (4, 5),
+            (5, 5),
+            (5, 4),
+            (5, 3),
+            (4, 3),
+            (3, 3),
+            (3, 4),
+            (3, 5),
+        ] {
+            assert!(b.valid_move(
+                (4, 4), // King in center
+                pos
+            ));
+        }
+        let b = Board::from_squares([
+            [
+                Some(Piece::new(Color::White, PieceType::Bishop)),
+                Some(Piece::new(Color::Black, PieceType::King)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+        ]);
+        assert!(b.valid_move((1, 0), (0, 0)));
+        assert!(b.valid_move((1, 0), (0, 1)));
+        assert!(!b.valid_move((1, 0), (1, 1)));
+    }
+
+    #[test]
+    fn square_positions_test() {
+        let b = Board::new();
+        let sq = |s: &str| s.parse::<square::Square>().unwrap();
+        assert_eq!(
+            b.piece_at(sq("e1")),
+            Some(Piece::new(Color::White, PieceType::King))
+        );
+        assert_eq!(b.piece_at(sq("e4")), None);
+        assert!(b.valid_move(sq("g8"), sq("f6")));
+        assert!(b.valid_move(sq("e2"), (4, 3)));
+        assert!(!b.valid_move(sq("e2"), sq("e5")));
+    }
+
+    #[test]
+    fn captures_test() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let b = Board::from_fen(fen).unwrap();
+            for color in [Color::White, Color::Black] {
+                let mut expected = b.legal_moves(color);
+                expected.retain(|m| m.flags.capture || m.promotion.is_some());
+                assert_eq!(b.captures(color), expected, "{}", fen);
+            }
+        }
+    }
+
+    #[test]
+    fn legal_moves_test() {
+        let b = Board::new();
+        assert_eq!(b.legal_moves(Color::White).len(), 20);
+        assert_eq!(b.legal_moves(Color::Black).len(), 20);
+        assert!(b
+            .legal_moves(Color::White)
+            .iter()
+            .all(|m| m.flags == MoveFlags::default() && m.promotion.is_none()));
+        let b = Board::from_squares([
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [
+                Some(Piece::new(Color::White, PieceType::Pawn)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+            [
+                None,
+                Some(Piece::new(Color::Black, PieceType::Rook)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+        ]);
+        let moves = b.legal_moves(Color::White);
+        // push or take the rook, each with four promotion choices
+        assert_eq!(moves.len(), 8);
+        assert_eq!(moves.iter().filter(|m| m.flags.capture).count(), 4);
+        for typ in PROMOTIONS {
+            assert!(moves.contains(&Move {
+                promotion: Some(typ),
+                flags: MoveFlags {
+                    capture: true,
+                    ..Default::default()
+                },
+                ..Move::new((0, 6), (1, 7))
+            }));
+        }
+        let mut b = Board::from_squares([
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [
+                Some(Piece::new(Color::Black, PieceType::Pawn)),
+                Some(Piece::new(Color::White, PieceType::Pawn)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+        ]);
+        b.passant_killable = Some((0, 4));
+        b.sync_from_squares();
+        let moves = b.legal_moves(Color::White);
+        assert_eq!(moves.len(), 2);
+        assert!(moves.contains(&Move {
+            flags: MoveFlags {
+                capture: true,
+                en_passant: true,
+                castle: false,
+            },
+            ..Move::new((1, 4), (0, 5))
+        }));
+    }
+
+    #[test]
+    fn castle_test() {
+        let castle_board = || {
+            Board::from_squares([
+                [
+                    Some(Piece::new(Color::White, PieceType::Rook)),
+                    None,
+                    None,
+                    None,
+                    Some(Piece::new(Color::White, PieceType::King)),
+                    None,
+                    None,
+                    Some(Piece::new(Color::White, PieceType::Rook)),
+                ],
+                [None; 8],
+                [None; 8],
+                [None; 8],
+                [None; 8],
+                [None; 8],
+                [None; 8],
+                [
+                    Some(Piece::new(Color::Black, PieceType::Rook)),
+                    None,
+                    None,
+                    None,
+                    Some(Piece::new(Color::Black, PieceType::King)),
+                    None,
+                    None,
+                    Some(Piece::new(Color::Black, PieceType::Rook)),
+                ],
+            ])
+        };
+        let b = castle_board();
+        assert!(b.valid_move((4, 0), (6, 0))); // white king side
+        assert!(b.valid_move((4, 0), (2, 0))); // white queen side
+        assert!(b.valid_move((4, 7), (6, 7))); // black king side
+        assert!(b.valid_move((4, 7), (2, 7))); // black queen side
+        assert!(!b.valid_move((4, 0), (4, 2))); // two squares forward is not a castle
+        assert!(b.legal_moves(Color::White).contains(&Move {
+            flags: MoveFlags {
+                castle: true,
+                ..Default::default()
+            },
+            ..Move::new((4, 0), (6, 0))
+        }));
+
+        // the rook jumps over the king
+        let mut b = castle_board();
+        b.make_move(Move::new((4, 0), (6, 0)));
+        assert_eq!(
+            b.board[0][5],
+            Some(Piece::new(Color::White, PieceType::Rook))
+        );
+        assert_eq!(
+            b.board[0][6],
+            Some(Piece::new(Color::White, PieceType::King))
+        );
+        assert_eq!(b.board[0][7], None);
+        assert!(!b.castle_rights.white_left && !b.castle_rights.white_right);
+        b.make_move(Move::new((4, 7), (2, 7)));
+        assert_eq!(
+            b.board[7][3],
+            Some(Piece::new(Color::Black, PieceType::Rook))
+        );
+        assert_eq!(
+            b.board[7][2],
+            Some(Piece::new(Color::Black, PieceType::King))
+        );
+        assert_eq!(b.board[7][0], None);
+        assert!(!b.castle_rights.black_left && !b.castle_rights.black_right);
+
+        // moving a rook away and back loses the right to castle with it
+        let mut b = castle_board();
+        b.make_move(Move::new((7, 0), (7, 1)));
+        b.make_move(Move::new((7, 1), (7, 0)));
+        assert!(!b.valid_move((4, 0), (6, 0)));
+        assert!(b.valid_move((4, 0), (2, 0)));
+
+        // losing a rook loses the right to castle with it
+        let mut b = castle_board();
+        b.make_move(Move::new((0, 7), (0, 0)));
+        assert!(!b.castle_rights.white_left && !b.castle_rights.black_left);
+        assert!(b.castle_rights.white_right && b.castle_rights.black_right);
+
+        // cannot castle through a piece
+        let mut b = castle_board();
+        b.set_square((1, 0), Some(Piece::new(Color::White, PieceType::Knight)));
+        assert!(!b.valid_move((4, 0), (2, 0)));
+
+        // cannot castle out of check
+        let mut b = castle_board();
+        b.set_square((4, 4), Some(Piece::new(Color::Black, PieceType::Rook)));
+        assert!(!b.valid_move((4, 0), (6, 0)));
+        assert!(!b.valid_move((4, 0), (2, 0)));
+
+        // cannot castle through check
+        let mut b = castle_board();
+        b.set_square((5, 4), Some(Piece::new(Color::Black, PieceType::Rook)));
+        assert!(!b.valid_move((4, 0), (6, 0)));
+        assert!(b.valid_move((4, 0), (2, 0)));
+
+        // cannot castle into check
+        let mut b = castle_board();
+        b.set_square((6, 4), Some(Piece::new(Color::Black, PieceType::Rook)));
+        assert!(!b.valid_move((4, 0), (6, 0)));
+        assert!(b.valid_move((4, 0), (2, 0)));
+    }
+
+    #[test]
+    fn promotion_test() {
+        let b = Board::from_squares([
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [
+                Some(Piece::new(Color::White, PieceType::Pawn)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+            [
+                None,
+                Some(Piece::new(Color::Black, PieceType::Rook)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+        ]);
+        let take = Move::new((0, 6), (1, 7));
+        assert!(!b.is_legal(take)); // must choose a piece
+        for typ in [PieceType::Pawn, PieceType::King] {
+            assert!(!b.is_legal(Move {
+                promotion: Some(typ),
+                ..take
+            }));
+        }
+        for typ in PROMOTIONS {
+            let m = Move {
+                promotion: Some(typ),
+                ..take
+            };
+            assert!(b.is_legal(m));
+            let b = b.test_move(m);
+            assert_eq!(b.board[7][1], Some(Piece::new(Color::White, typ)));
+            assert_eq!(b.board[6][0], None);
+        }
+        assert!(b.is_legal(Move {
+            promotion: Some(PieceType::Knight),
+            ..Move::new((0, 6), (0, 7))
+        }));
+        // only a pawn on the last rank may promote
+        let b = Board::new();
+        assert!(b.is_legal(Move::new((4, 1), (4, 3))));
+        assert!(!b.is_legal(Move {
+            promotion: Some(PieceType::Queen),
+            ..Move::new((4, 1), (4, 3))
+        }));
+        assert!(!b.is_legal(Move {
+            promotion: Some(PieceType::Queen),
+            ..Move::new((6, 0), (5, 2))
+        }));
+    }
+
+    #[test]
+    fn en_passant_test() {
+        let mut b = Board::new();
+        b.make_move(Move::new((4, 1), (4, 3)));
+        assert_eq!(b.passant_killable, Some((4, 3)));
+        b.make_move(Move::new((0, 6), (0, 5)));
+        assert_eq!(b.passant_killable, None); // only lasts one ply
+        b.make_move(Move::new((4, 3), (4, 4)));
+        b.make_move(Move::new((3, 6), (3, 4)));
+        assert_eq!(b.passant_killable, Some((3, 4)));
+        assert!(b.valid_move((4, 4), (3, 5)));
+        assert!(!b.valid_move((4, 4), (5, 5))); // nothing to take on the other side
+        let m = Move::new((4, 4), (3, 5));
+        assert!(b.legal_moves(Color::White).contains(&Move {
+            flags: MoveFlags {
+                capture: true,
+                en_passant: true,
+                castle: false,
+            },
+            ..m
+        }));
+        b.make_move(m);
+        assert_eq!(
+            b.board[5][3],
+            Some(Piece::new(Color::White, PieceType::Pawn))
+        );
+        assert_eq!(b.board[4][3], None); // the black pawn is gone
+        assert_eq!(b.board[4][4], None);
+        assert_eq!(b.passant_killable, None);
+
+        // too late to take en passant once another move has been played
+        let mut b = Board::new();
+        b.make_move(Move::new((4, 1), (4, 3)));
+        b.make_move(Move::new((0, 6), (0, 5)));
+        b.make_move(Move::new((4, 3), (4, 4)));
+        b.make_move(Move::new((3, 6), (3, 4)));
+        b.make_move(Move::new((0, 1), (0, 2)));
+        b.make_move(Move::new((0, 5), (0, 4)));
+        assert!(!b.valid_move((4, 4), (3, 5)));
+
+        // taking en passant may not expose the king along the rank
+        let mut b = Board::from_squares([
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [
+                Some(Piece::new(Color::White, PieceType::King)),
+                None,
+                None,
+                Some(Piece::new(Color::White, PieceType::Pawn)),
+                Some(Piece::new(Color::Black, PieceType::Pawn)),
+                None,
+                None,
+                Some(Piece::new(Color::Black, PieceType::Rook)),
+            ],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+        ]);
+        b.passant_killable = Some((4, 4));
+        b.sync_from_squares();
+        assert!(b.valid_move((3, 4), (3, 5)));
+        assert!(!b.valid_move((3, 4), (4, 5)));
+    }
+
+    #[test]
+    fn play_test() {
+        let mut b = Board::new();
+        assert_eq!(b.turn(), Color::White);
+        assert_eq!(b.play(Move::new((4, 3), (4, 4))), Err(MoveError::NoPiece));
+        assert_eq!(
+            b.play(Move::new((4, 6), (4, 4))),
+            Err(MoveError::WrongColor)
+        );
+        assert_eq!(
+            b.play(Move::new((3, 1), (3, 4))),
+            Err(MoveError::IllegalMove)
+        );
+        assert!(b.play(Move::new((3, 1), (3, 3))).is_ok());
+        assert_eq!(b.turn(), Color::Black);
+        assert_eq!(
+            b.play(Move::new((3, 3), (3, 4))),
+            Err(MoveError::WrongColor)
+        );
+        assert!(b.play(Move::new((4, 6), (4, 5))).is_ok());
+        assert_eq!((b.halfmove_clock(), b.fullmove_number()), (0, 2));
+        assert!(b.play(Move::new((0, 1), (0, 2))).is_ok());
+        assert!(b.play(Move::new((5, 7), (1, 3))).is_ok()); // check
+        assert_eq!((b.halfmove_clock(), b.fullmove_number()), (1, 3));
+        assert_eq!(
+            b.play(Move::new((7, 1), (7, 2))),
+            Err(MoveError::LeavesKingInCheck)
+        );
+        assert_eq!(
+            b.play(Move::new((2, 1), (2, 2))),
+            Ok(Move::new((2, 1), (2, 2)))
+        );
+        assert_eq!((b.halfmove_clock(), b.fullmove_number()), (0, 3));
+        assert!(b.play(Move::new((6, 7), (5, 5))).is_ok());
+        assert_eq!(
+            b.play(Move::new((0, 2), (1, 3))),
+            Ok(Move {
+                flags: MoveFlags {
+                    capture: true,
+                    ..Default::default()
+                },
+                ..Move::new((0, 2), (1, 3))
+            })
+        );
+        assert_eq!(b.turn(), Color::Black);
+        assert!(b.play(Move::new((1, 7), (2, 5))).is_ok());
+        assert!(b.play(Move::new((6, 0), (5, 2))).is_ok());
+        assert_eq!((b.halfmove_clock(), b.fullmove_number()), (2, 5));
+        assert_eq!(b.turn(), Color::Black);
+    }
+
+    #[test]
+    fn in_check_test() {
+        let b = Board::from_squares([
+            [
+                Some(Piece::new(Color::White, PieceType::King)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(Piece::new(Color::Black, PieceType::Queen)),
+                Some(Piece::new(Color::Black, PieceType::King)),
+            ],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+        ]);
+        assert!(b.in_check(Color::White));
+        assert!(!b.in_check(Color::Black));
+    }
+
+    #[test]
+    fn pinned_check_test() {
+        // the black queen is pinned to its king but still gives check
+        let b = Board::from_fen("6k1/8/8/8/8/8/6q1/5KR1 w - - 0 1").unwrap();
+        assert!(b.in_check(Color::White));
+        let b =
+            Board::from_fen("2kr2nr/p2n1ppp/B1p1p3/8/1P1P1B2/2N4P/1PPK1PP1/7q b - - 1 14").unwrap();
+        assert!(b.in_check(Color::Black));
+        assert!(!b.has_legal_move(Color::Black));
+    }
+
+    #[test]
+    fn put_self_in_check() {
+        let b = Board::from_squares([
+            [
+                Some(Piece::new(Color::White, PieceType::King)),
+                Some(Piece::new(Color::White, PieceType::Queen)),
+                None,
+                None,
+                None,
+                None,
+                Some(Piece::new(Color::Black, PieceType::Queen)),
+                Some(Piece::new(Color::Black, PieceType::King)),
+            ],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+        ]);
+        assert!(!b.valid_move((1, 0), (1, 1)));
+        assert!(!b.valid_move((6, 0), (6, 1)));
+    }
+
+    /// Make and take back every move a couple of plies deep, checking that the board
+    /// always comes back exactly as it was
+    fn make_unmake(b: &mut Board, depth: u32) {
+        let before = *b;
+        for m in b.legal_moves(b.turn) {
+            let undo = b.make_move(m);
+            assert_eq!(*b, before.test_move(m));
+            if depth > 1 {
+                make_unmake(b, depth - 1);
+            }
+            b.unmake_move(m, undo);
+            assert_eq!(*b, before, "{} after {}", before.to_fen(), m.to_uci());
+        }
+    }
+
+    #[test]
+    fn unmake_move_test() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "4k3/8/8/8/3pP3/8/8/4K3 b - e3 7 40",
+        ] {
+            make_unmake(&mut Board::from_fen(fen).unwrap(), 2);
+        }
+
+        // a capture that also promotes and takes away a castling right
+        let mut b = Board::from_fen("r3k2r/1P6/8/8/8/8/8/4K3 w kq - 3 20").unwrap();
+        let before = b;
+        let m = b.parse_uci("b7a8q").unwrap();
+        let undo = b.make_move(m);
+        assert_eq!(b.to_fen(), "Q3k2r/8/8/8/8/8/8/4K3 b k - 0 20");
+        b.unmake_move(m, undo);
+        assert_eq!(b, before);
+    }
+}
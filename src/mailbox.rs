@@ -0,0 +1,170 @@
+//! The original square by square move generator, from before bitboards. It is slow
+//! but simple, so it is kept around to check the bitboard one against and to
+//! benchmark it with. It is only built for tests and with the `bench` feature.
+
+use crate::moves::*;
+use crate::pieces::*;
+use crate::{Board, Position};
+
+impl Board {
+    /// `in_check` by trying every enemy piece against the king
+    pub fn mailbox_in_check(&self, color: Color) -> bool {
+        let king = match self.find_king(color) {
+            Some(king) => king,
+            // cannot be in check with no king
+            None => return false,
+        };
+        for y in 0..8 {
+            for x in 0..8 {
+                if matches!(
+                    self.board[y][x],
+                    Some(Piece { color: c, ..}) if color != c
+                    // a pinned piece still gives check, so only the pattern matters
+                ) && self.valid_pattern((x, y), king)
+                {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// `legal_moves` by trying every square each piece could reach by its pattern
+    pub fn mailbox_legal_moves(&self, color: Color) -> Vec<Move> {
+        let mut moves = Vec::new();
+        for y in 0..8 {
+            for x in 0..8 {
+                let piece = match self.board[y][x] {
+                    Some(p) if p.color == color => p,
+                    _ => continue,
+                };
+                for to in targets(piece, (x, y)) {
+                    if !self.valid_pattern((x, y), to)
+                        || self
+                            .test_move(Move::new((x, y), to))
+                            .mailbox_in_check(color)
+                    {
+                        continue;
+                    }
+                    let m = Move {
+                        flags: self.move_flags((x, y), to),
+                        ..Move::new((x, y), to)
+                    };
+                    if piece.typ == PieceType::Pawn && (to.1 == 0 || to.1 == 7) {
+                        moves.extend(PROMOTIONS.map(|typ| Move {
+                            promotion: Some(typ),
+                            ..m
+                        }));
+                    } else {
+                        moves.push(m);
+                    }
+                }
+            }
+        }
+        moves
+    }
+}
+
+/// Move `pos` by `(dx, dy)` returning `None` if it falls off the board
+fn offset(pos: Position, dx: isize, dy: isize) -> Option<Position> {
+    let x = pos.0.checked_add_signed(dx)?;
+    let y = pos.1.checked_add_signed(dy)?;
+    (x < 8 && y < 8).then_some((x, y))
+}
+
+/// Every square `piece` could reach from `from` by its movement pattern alone.
+/// Blocking pieces and checks are ignored, that is left to `Board::valid_move`.
+fn targets(piece: Piece, from: Position) -> Vec<Position> {
+    const KNIGHT: [(isize, isize); 8] = [
+        (1, 2),
+        (2, 1),
+        (2, -1),
+        (1, -2),
+        (-1, -2),
+        (-2, -1),
+        (-2, 1),
+        (-1, 2),
+    ];
+    const ROOK: [(isize, isize); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+    const BISHOP: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+    const KING: [(isize, isize); 8] = [
+        (0, 1),
+        (1, 1),
+        (1, 0),
+        (1, -1),
+        (0, -1),
+        (-1, -1),
+        (-1, 0),
+        (-1, 1),
+    ];
+    let rays = |dirs: &[(isize, isize)]| {
+        let mut list = Vec::new();
+        for &(dx, dy) in dirs {
+            let mut curr = from;
+            while let Some(next) = offset(curr, dx, dy) {
+                list.push(next);
+                curr = next;
+            }
+        }
+        list
+    };
+    let steps = |dirs: &[(isize, isize)]| {
+        dirs.iter()
+            .filter_map(|&(dx, dy)| offset(from, dx, dy))
+            .collect::<Vec<_>>()
+    };
+    match piece.typ {
+        PieceType::Pawn => {
+            let dy = if piece.color == Color::White { 1 } else { -1 };
+            steps(&[(0, dy), (0, dy * 2), (1, dy), (-1, dy)])
+        }
+        PieceType::Rook => rays(&ROOK),
+        PieceType::Knight => steps(&KNIGHT),
+        PieceType::Bishop => rays(&BISHOP),
+        PieceType::Queen => rays(&KING),
+        PieceType::King => [steps(&KING), steps(&[(2, 0), (-2, 0)])].concat(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Walk the first few moves of every line from `board`, checking both
+    /// generators agree at each position along the way
+    fn compare(board: &Board, depth: u32) {
+        let turn = board.turn();
+        let mut fast = board.legal_moves(turn);
+        let mut slow = board.mailbox_legal_moves(turn);
+        let key = |m: &Move| (m.from, m.to, m.promotion.map(|p| p.letter()));
+        fast.sort_by_key(key);
+        slow.sort_by_key(key);
+        assert_eq!(fast, slow, "{}", board.to_fen());
+        for color in [Color::White, Color::Black] {
+            assert_eq!(
+                board.in_check(color),
+                board.mailbox_in_check(color),
+                "{}",
+                board.to_fen()
+            );
+        }
+        if depth > 0 {
+            for m in fast {
+                compare(&board.test_move(m), depth - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn generators_agree_test() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "6k1/8/8/8/8/8/6q1/5KR1 w - - 0 1",
+        ] {
+            compare(&Board::from_fen(fen).unwrap(), 1);
+        }
+    }
+}
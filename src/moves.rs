@@ -0,0 +1,84 @@
+use crate::pieces::*;
+use crate::Position;
+use std::{error, fmt};
+
+/// Pieces a pawn may promote to, strongest first
+pub const PROMOTIONS: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+/// Extra information about what a move does to the board
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MoveFlags {
+    pub capture: bool,
+    pub en_passant: bool,
+    pub castle: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: Position,
+    pub to: Position,
+    /// The piece a pawn becomes when it reaches the last rank
+    pub promotion: Option<PieceType>,
+    pub flags: MoveFlags,
+}
+
+impl Move {
+    pub fn new(from: Position, to: Position) -> Self {
+        Self {
+            from,
+            to,
+            promotion: None,
+            flags: MoveFlags::default(),
+        }
+    }
+}
+
+/// What `Board::make_move` changed, so `Board::unmake_move` can put it all back
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Undo {
+    /// The piece that moved, as it was before any promotion
+    pub(crate) moved: Option<Piece>,
+    /// The piece taken and where it stood, which is not the target square en passant
+    pub(crate) captured: Option<(Position, Piece)>,
+    /// Where the rook came from and went to when castling
+    pub(crate) rook: Option<(Position, Position)>,
+    pub(crate) castle_rights: crate::CastleRights,
+    pub(crate) passant_killable: Option<Position>,
+    pub(crate) turn: Color,
+    pub(crate) halfmove_clock: u32,
+    pub(crate) fullmove_number: u32,
+    pub(crate) hash: u64,
+}
+
+/// Reasons `Board::play` can refuse a move
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    NoPiece,
+    WrongColor,
+    IllegalMove,
+    LeavesKingInCheck,
+    MissingPromotion,
+    InvalidPromotion,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use MoveError::*;
+        let s = match self {
+            NoPiece => "there is no piece on that square",
+            WrongColor => "that piece belongs to the other player",
+            IllegalMove => "that piece cannot move there",
+            LeavesKingInCheck => "that move would leave the king in check",
+            MissingPromotion => "a pawn reaching the last rank must promote",
+            InvalidPromotion => "that promotion is not allowed",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl error::Error for MoveError {}
@@ -0,0 +1,181 @@
+use crate::bitboard::{color_index, square};
+use crate::engine::MAX_PLY;
+use crate::eval::piece_value;
+use crate::moves::Move;
+use crate::pieces::*;
+use crate::Board;
+
+/// Sort keys for each kind of move, best first. Captures come before killers
+/// and killers before other quiet moves, however much history those have.
+const HASH_MOVE: i32 = i32::MAX;
+const CAPTURE: i32 = 2_000_000;
+const KILLER: i32 = 1_000_000;
+/// History scores are halved once one gets this big, keeping them under `KILLER`
+const HISTORY_MAX: i32 = 500_000;
+
+/// Most valuable victim, least valuable attacker. Taking a queen with a pawn
+/// sorts first and taking a pawn with a queen last. Promotions count the piece
+/// gained as part of the victim.
+pub fn mvv_lva(board: &Board, m: Move) -> i32 {
+    let victim = if m.flags.en_passant {
+        Some(PieceType::Pawn)
+    } else {
+        board.piece_at(m.to).map(|p| p.typ)
+    };
+    let gain = victim.map_or(0, piece_value)
+        + m.promotion
+            .map_or(0, |typ| piece_value(typ) - piece_value(PieceType::Pawn));
+    let attacker = board.piece_at(m.from).map_or(0, |p| piece_value(p.typ));
+    // victims differ by more than any attacker is worth
+    gain * 100 - attacker
+}
+
+fn is_quiet(m: Move) -> bool {
+    !m.flags.capture && m.promotion.is_none()
+}
+
+/// What the search has learned about which moves cause cutoffs, used to try those first
+#[derive(Debug, Clone)]
+pub struct MoveOrdering {
+    /// Two quiet moves per ply that refuted another line at the same ply
+    killers: Vec<[Option<Move>; 2]>,
+    /// How often each quiet move caused a cutoff, weighted by depth, by color, from and to
+    history: Vec<[[i32; 64]; 64]>,
+}
+
+impl Default for MoveOrdering {
+    fn default() -> Self {
+        Self {
+            killers: vec![[None; 2]; MAX_PLY as usize],
+            history: vec![[[0; 64]; 64]; 2],
+        }
+    }
+}
+
+impl MoveOrdering {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the killers, which belong to the position searched before,
+    /// and weaken the history so the new search can outweigh it
+    pub fn new_search(&mut self) {
+        self.killers.fill([None; 2]);
+        self.age_history();
+    }
+
+    fn age_history(&mut self) {
+        for score in self.history.iter_mut().flatten().flatten() {
+            *score /= 2;
+        }
+    }
+
+    pub fn killers(&self, ply: u32) -> [Option<Move>; 2] {
+        self.killers.get(ply as usize).copied().unwrap_or([None; 2])
+    }
+
+    pub fn history(&self, color: Color, m: Move) -> i32 {
+        self.history[color_index(color)][square(m.from).index()][square(m.to).index()]
+    }
+
+    /// Note that `m`, played by `color` at `ply`, caused a beta cutoff `depth` plies
+    /// from the leaves. Only quiet moves are remembered, captures sort well anyway.
+    pub fn cutoff(&mut self, color: Color, m: Move, ply: u32, depth: u32) {
+        if !is_quiet(m) {
+            return;
+        }
+        if let Some(killers) = self.killers.get_mut(ply as usize) {
+            if killers[0] != Some(m) {
+                killers[1] = killers[0];
+                killers[0] = Some(m);
+            }
+        }
+        let score =
+            &mut self.history[color_index(color)][square(m.from).index()][square(m.to).index()];
+        *score += (depth * depth) as i32;
+        if *score > HISTORY_MAX {
+            self.age_history();
+        }
+    }
+
+    /// How early `m` should be tried, higher first
+    pub fn score(&self, board: &Board, m: Move, hash_move: Option<Move>, ply: u32) -> i32 {
+        if hash_move == Some(m) {
+            HASH_MOVE
+        } else if !is_quiet(m) {
+            CAPTURE + mvv_lva(board, m)
+        } else {
+            match self.killers(ply) {
+                [Some(k), _] if k == m => KILLER,
+                [_, Some(k)] if k == m => KILLER - 1,
+                _ => self.history(board.turn(), m),
+            }
+        }
+    }
+
+    /// Sort `moves` for `board` so the ones most likely to cause a cutoff come first:
+    /// the hash move, then captures and promotions by MVV-LVA, then killers, then
+    /// the other quiet moves by history
+    pub fn order(&self, board: &Board, moves: &mut [Move], hash_move: Option<Move>, ply: u32) {
+        moves.sort_by_cached_key(|&m| -self.score(board, m, hash_move, ply));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uci(board: &Board, s: &str) -> Move {
+        board.parse_uci(s).unwrap()
+    }
+
+    #[test]
+    fn mvv_lva_test() {
+        // the queen on d5 can be taken by the pawn or the knight
+        let b = Board::from_fen("4k3/8/8/3q4/2P2N2/8/8/4K3 w - - 0 1").unwrap();
+        assert!(mvv_lva(&b, uci(&b, "c4d5")) > mvv_lva(&b, uci(&b, "f4d5")));
+
+        let b = Board::from_fen("4k3/8/2nq4/1Pr5/4N3/8/8/4K3 w - - 0 1").unwrap();
+        let nxq = mvv_lva(&b, uci(&b, "e4d6"));
+        let nxr = mvv_lva(&b, uci(&b, "e4c5"));
+        let pxn = mvv_lva(&b, uci(&b, "b5c6"));
+        assert!(nxq > nxr && nxr > pxn);
+
+        let b = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(mvv_lva(&b, uci(&b, "b7b8q")) > mvv_lva(&b, uci(&b, "b7b8n")));
+        let b = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(
+            mvv_lva(&b, uci(&b, "e5d6")),
+            piece_value(PieceType::Pawn) * 99
+        );
+    }
+
+    #[test]
+    fn order_test() {
+        let b = Board::from_fen("4k3/8/2nq4/1Pr5/4N3/8/6P1/4K3 w - - 0 1").unwrap();
+        let mut ordering = MoveOrdering::new();
+        let killer = uci(&b, "g2g4");
+        let history = uci(&b, "e1f2");
+        ordering.cutoff(Color::White, killer, 3, 1);
+        ordering.cutoff(Color::White, history, 2, 4);
+        // captures are never killers
+        ordering.cutoff(Color::White, uci(&b, "b5c6"), 3, 1);
+        assert_eq!(ordering.killers(3), [Some(killer), None]);
+        assert_eq!(ordering.history(Color::White, history), 16);
+        assert_eq!(ordering.history(Color::Black, history), 0);
+
+        let mut moves = b.legal_moves(Color::White);
+        ordering.order(&b, &mut moves, Some(uci(&b, "e1f1")), 3);
+        let expected = ["e1f1", "e4d6", "e4c5", "b5c6", "g2g4", "e1f2"].map(|s| uci(&b, s));
+        assert_eq!(moves[..6], expected);
+
+        // killers belong to their ply
+        ordering.order(&b, &mut moves, None, 4);
+        assert_eq!(moves[3], history);
+
+        // a fresh search forgets killers but remembers some history
+        ordering.new_search();
+        assert_eq!(ordering.killers(3), [None, None]);
+        assert_eq!(ordering.history(Color::White, history), 8);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::moves::*;
+use crate::Board;
+
+impl Board {
+    /// Count the leaf nodes of the legal move tree `depth` plies deep, the usual
+    /// way of checking a move generator against known results
+    pub fn perft(&self, depth: u32) -> u64 {
+        let mut board = *self;
+        board.perft_in_place(depth)
+    }
+
+    /// Perft split up by the first move, handy for finding which move another
+    /// generator disagrees about
+    pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        let mut board = *self;
+        self.legal_moves(self.turn)
+            .into_iter()
+            .map(|m| {
+                let undo = board.make_move(m);
+                let nodes = board.perft_in_place(depth.saturating_sub(1));
+                board.unmake_move(m, undo);
+                (m, nodes)
+            })
+            .collect()
+    }
+
+    fn perft_in_place(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.legal_moves(self.turn);
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for m in moves {
+            let undo = self.make_move(m);
+            nodes += self.perft_in_place(depth - 1);
+            self.unmake_move(m, undo);
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    /// Reference positions with their known node counts from depth 1 up
+    const POSITIONS: [(&str, &[u64]); 6] = [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            &[20, 400, 8902, 197281, 4865609],
+        ),
+        (KIWIPETE, &[48, 2039, 97862, 4085603]),
+        (
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            &[14, 191, 2812, 43238, 674624],
+        ),
+        (
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            &[6, 264, 9467, 422333],
+        ),
+        (
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            &[44, 1486, 62379, 2103487],
+        ),
+        (
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            &[46, 2079, 89890, 3894594],
+        ),
+    ];
+
+    fn check(max_nodes: u64) {
+        for (fen, counts) in POSITIONS {
+            let b = Board::from_fen(fen).unwrap();
+            for (depth, &nodes) in (1..).zip(counts).filter(|&(_, &n)| n <= max_nodes) {
+                assert_eq!(b.perft(depth), nodes, "depth {} of {}", depth, fen);
+            }
+        }
+    }
+
+    #[test]
+    fn perft_test() {
+        check(100_000);
+    }
+
+    #[test]
+    #[ignore = "takes a while, run with --ignored"]
+    fn perft_deep_test() {
+        check(u64::MAX);
+    }
+
+    #[test]
+    fn divide_test() {
+        let b = Board::from_fen(KIWIPETE).unwrap();
+        let divide = b.divide(2);
+        assert_eq!(divide.len(), 48);
+        assert_eq!(divide.iter().map(|&(_, n)| n).sum::<u64>(), 2039);
+        let castle = divide.iter().find(|(m, _)| m.to_uci() == "e1g1").unwrap();
+        assert_eq!(castle.1, 43);
+        assert_eq!(Board::new().divide(0).len(), 20);
+    }
+}
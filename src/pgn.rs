@@ -0,0 +1,650 @@
+use crate::fen::FenError;
+use crate::game::Game;
+use crate::pieces::*;
+use crate::san::SanError;
+use crate::Board;
+use std::{error, fmt};
+
+/// Lines written by `PgnGame::to_pgn` never go past this many characters
+pub const LINE_WIDTH: usize = 80;
+
+/// Tags every game is written with, in this order, before any others
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum PgnResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// The game is unfinished or the result is not known
+    #[default]
+    Unknown,
+}
+
+impl PgnResult {
+    fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "1-0" => PgnResult::WhiteWins,
+            "0-1" => PgnResult::BlackWins,
+            "1/2-1/2" => PgnResult::Draw,
+            "*" => PgnResult::Unknown,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for PgnResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            PgnResult::WhiteWins => "1-0",
+            PgnResult::BlackWins => "0-1",
+            PgnResult::Draw => "1/2-1/2",
+            PgnResult::Unknown => "*",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A move in the game tree along with everything written about it
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PgnMove {
+    pub san: String,
+    /// Numeric annotation glyphs, `!` and `?` style suffixes are read as these
+    pub nags: Vec<u8>,
+    /// A comment written before the move, only possible at the start of a line
+    pub comment_before: Option<String>,
+    pub comment: Option<String>,
+    /// Lines played instead of this move, each starting from the same position as it
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(san: &str) -> Self {
+        Self {
+            san: san.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+    /// Tag pairs in the order they were read
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<PgnMove>,
+    pub result: PgnResult,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    /// A tag pair on the given line is not shaped like `[Name "value"]`
+    InvalidTag(usize),
+    /// A comment opened on the given line never closes
+    UnterminatedComment(usize),
+    /// The variation opened on the given line never closes
+    UnterminatedVariation(usize),
+    /// A `)` on the given line has no `(` to close
+    UnmatchedParen(usize),
+    /// A variation or annotation on the given line comes before any move
+    NothingToAnnotate(usize),
+    InvalidNag(usize),
+    InvalidFen(FenError),
+    IllegalMove(SanError),
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use PgnError::*;
+        match self {
+            InvalidTag(line) => write!(f, "line {}: malformed tag pair", line),
+            UnterminatedComment(line) => write!(f, "line {}: comment is never closed", line),
+            UnterminatedVariation(line) => write!(f, "line {}: variation is never closed", line),
+            UnmatchedParen(line) => write!(f, "line {}: ')' without a matching '('", line),
+            NothingToAnnotate(line) => {
+                write!(f, "line {}: variation or annotation before any move", line)
+            }
+            InvalidNag(line) => write!(f, "line {}: invalid numeric annotation glyph", line),
+            InvalidFen(err) => write!(f, "invalid FEN tag: {}", err),
+            IllegalMove(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for PgnError {}
+
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    Open,
+    Close,
+    Result(PgnResult),
+    San(String),
+}
+
+/// Split PGN text into tokens, each with the line it starts on
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, PgnError> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        let start = line;
+        let was_line_start = line_start;
+        line_start = c == '\n';
+        match c {
+            '\n' => line += 1,
+            c if c.is_whitespace() => line_start = was_line_start,
+            // escape mechanism, the rest of the line is ignored
+            '%' if was_line_start => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line += 1;
+                        line_start = true;
+                        break;
+                    }
+                }
+            }
+            ';' => {
+                let comment = chars
+                    .by_ref()
+                    .take_while(|&c| c != '\n')
+                    .collect::<String>();
+                line += 1;
+                line_start = true;
+                tokens.push((start, Token::Comment(comment.trim().to_string())));
+            }
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            comment.push(c);
+                        }
+                        None => return Err(PgnError::UnterminatedComment(start)),
+                    }
+                }
+                let words = comment.split_whitespace().collect::<Vec<_>>();
+                tokens.push((start, Token::Comment(words.join(" "))));
+            }
+            '[' => {
+                let mut tag = String::new();
+                let mut in_string = false;
+                loop {
+                    match chars.next() {
+                        Some(']') if !in_string => break,
+                        Some('"') => {
+                            in_string = !in_string;
+                            tag.push('"');
+                        }
+                        Some('\\') if in_string => {
+                            tag.push('\\');
+                            tag.extend(chars.next());
+                        }
+                        Some('\n') | None => return Err(PgnError::InvalidTag(start)),
+                        Some(c) => tag.push(c),
+                    }
+                }
+                let (name, value) = parse_tag(&tag).ok_or(PgnError::InvalidTag(start))?;
+                tokens.push((start, Token::Tag(name, value)));
+            }
+            '(' => tokens.push((start, Token::Open)),
+            ')' => tokens.push((start, Token::Close)),
+            '$' => {
+                let mut digits = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+                    digits.push(c);
+                    chars.next();
+                }
+                let nag = digits.parse().map_err(|_| PgnError::InvalidNag(start))?;
+                tokens.push((start, Token::Nag(nag)));
+            }
+            c => {
+                let mut symbol = c.to_string();
+                while let Some(&c) = chars
+                    .peek()
+                    .filter(|&&c| !c.is_whitespace() && !"{}()[];$".contains(c))
+                {
+                    symbol.push(c);
+                    chars.next();
+                }
+                tokens.extend(symbol_tokens(&symbol).into_iter().map(|t| (start, t)));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Read the inside of a tag pair like `Event "Casual game"`
+fn parse_tag(tag: &str) -> Option<(String, String)> {
+    let tag = tag.trim();
+    let (name, value) = tag.split_once(char::is_whitespace)?;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut unescaped = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            '"' => return None,
+            c => unescaped.push(c),
+        }
+    }
+    Some((name.to_string(), unescaped))
+}
+
+/// Turn a symbol into a result, or a move with its number and suffix annotations dropped
+fn symbol_tokens(symbol: &str) -> Vec<Token> {
+    if let Some(result) = PgnResult::parse(symbol) {
+        return vec![Token::Result(result)];
+    }
+    // a move number is digits followed by one or more dots, and may run into the move itself
+    let digits = symbol.trim_start_matches(|c: char| c.is_ascii_digit());
+    let san = match digits.strip_prefix('.') {
+        Some(rest) if digits.len() < symbol.len() => rest.trim_start_matches('.'),
+        _ => symbol,
+    };
+    if san.is_empty() {
+        return Vec::new();
+    }
+    let stripped = san.trim_end_matches(['!', '?']);
+    let nag = match &san[stripped.len()..] {
+        "" => None,
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    };
+    let mut tokens = vec![Token::San(stripped.to_string())];
+    tokens.extend(nag.map(Token::Nag));
+    tokens
+}
+
+/// Read every game in a PGN file
+pub fn parse(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games = Vec::new();
+    let mut game = PgnGame::default();
+    // the main line followed by any variations currently open, and the line each was opened on
+    let mut lines: Vec<(usize, Vec<PgnMove>)> = vec![(1, Vec::new())];
+    let mut pending_comment: Option<String> = None;
+    let mut started = false;
+    for (line, token) in tokenize(text)? {
+        let (_, current) = lines.last_mut().expect("the main line is never closed");
+        match token {
+            Token::Tag(name, value) => {
+                if !lines[0].1.is_empty() {
+                    // a game without a result, the tags belong to the next one
+                    if lines.len() > 1 {
+                        return Err(PgnError::UnterminatedVariation(lines[1].0));
+                    }
+                    game.moves = std::mem::take(&mut lines[0].1);
+                    games.push(std::mem::take(&mut game));
+                }
+                game.tags.push((name, value));
+                started = true;
+            }
+            Token::Comment(comment) => match current.last_mut() {
+                Some(m) => {
+                    m.comment = Some(match m.comment.take() {
+                        Some(prev) => format!("{} {}", prev, comment),
+                        None => comment,
+                    })
+                }
+                None => {
+                    pending_comment = Some(match pending_comment.take() {
+                        Some(prev) => format!("{} {}", prev, comment),
+                        None => comment,
+                    })
+                }
+            },
+            Token::Nag(nag) => current
+                .last_mut()
+                .ok_or(PgnError::NothingToAnnotate(line))?
+                .nags
+                .push(nag),
+            Token::San(san) => {
+                current.push(PgnMove {
+                    comment_before: pending_comment.take(),
+                    ..PgnMove::new(&san)
+                });
+                started = true;
+            }
+            Token::Open => {
+                if current.is_empty() {
+                    return Err(PgnError::NothingToAnnotate(line));
+                }
+                lines.push((line, Vec::new()));
+            }
+            Token::Close => {
+                if lines.len() == 1 {
+                    return Err(PgnError::UnmatchedParen(line));
+                }
+                let (_, variation) = lines.pop().unwrap();
+                let (_, parent) = lines.last_mut().unwrap();
+                parent.last_mut().unwrap().variations.push(variation);
+            }
+            Token::Result(result) => {
+                if lines.len() > 1 {
+                    return Err(PgnError::UnterminatedVariation(lines[1].0));
+                }
+                game.result = result;
+                game.moves = std::mem::take(&mut lines[0].1);
+                games.push(std::mem::take(&mut game));
+                pending_comment = None;
+                started = false;
+            }
+        }
+    }
+    if lines.len() > 1 {
+        return Err(PgnError::UnterminatedVariation(lines[1].0));
+    }
+    if started {
+        game.moves = std::mem::take(&mut lines[0].1);
+        games.push(game);
+    }
+    Ok(games)
+}
+
+/// Write several games one after the other, separated by blank lines
+pub fn write(games: &[PgnGame]) -> String {
+    games
+        .iter()
+        .map(PgnGame::to_pgn)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Push the words of a comment as separate tokens so long comments can be wrapped
+fn comment_tokens(tokens: &mut Vec<String>, comment: &str) {
+    let words = comment.split_whitespace().collect::<Vec<_>>();
+    match words[..] {
+        [] => tokens.push("{}".to_string()),
+        [word] => tokens.push(format!("{{{}}}", word)),
+        [first, .., last] => {
+            tokens.push(format!("{{{}", first));
+            tokens.extend(words[1..words.len() - 1].iter().map(|w| w.to_string()));
+            tokens.push(format!("{}}}", last));
+        }
+    }
+}
+
+impl PgnGame {
+    /// The value of the first tag called `name`
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The position the game starts from, given by the FEN tag if there is one
+    pub fn start_position(&self) -> Result<Board, PgnError> {
+        match self.tag("FEN") {
+            Some(fen) => Board::from_fen(fen).map_err(PgnError::InvalidFen),
+            None => Ok(Board::new()),
+        }
+    }
+
+    /// Play through the main line, checking that every variation is legal along the way
+    pub fn replay(&self) -> Result<Game, PgnError> {
+        fn check_line(
+            board: &Board,
+            line: &[PgnMove],
+            game: Option<&mut Game>,
+        ) -> Result<(), PgnError> {
+            let mut board = *board;
+            let mut game = game;
+            for pgn_move in line {
+                for variation in &pgn_move.variations {
+                    check_line(&board, variation, None)?;
+                }
+                let m = board
+                    .parse_san(&pgn_move.san)
+                    .map_err(PgnError::IllegalMove)?;
+                board.make_move(m);
+                if let Some(game) = game.as_deref_mut() {
+                    game.play(m).expect("parse_san only returns legal moves");
+                }
+            }
+            Ok(())
+        }
+        let board = self.start_position()?;
+        let mut game = Game::from_board(board);
+        check_line(&board, &self.moves, Some(&mut game))?;
+        Ok(game)
+    }
+
+    /// Write the game as PGN with the seven tag roster first and movetext wrapped to `LINE_WIDTH`
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        let escape = |v: &str| v.replace('\\', "\\\\").replace('"', "\\\"");
+        for (name, default) in SEVEN_TAG_ROSTER {
+            let value = if name == "Result" {
+                self.result.to_string()
+            } else {
+                self.tag(name).unwrap_or(default).to_string()
+            };
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(&value)));
+        }
+        for (name, value) in &self.tags {
+            if !SEVEN_TAG_ROSTER.iter().any(|(n, _)| n == name) {
+                pgn.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+            }
+        }
+        pgn.push('\n');
+
+        // plies are counted from white's first move so black to move starts at ply 1
+        let (turn, fullmove) = self
+            .start_position()
+            .map(|b| (b.turn(), b.fullmove_number()))
+            .unwrap_or((Color::White, 1));
+        let first_ply = (fullmove as usize - 1) * 2 + (turn == Color::Black) as usize;
+        let mut tokens = Vec::new();
+        write_line(&mut tokens, &self.moves, first_ply);
+        tokens.push(self.result.to_string());
+
+        let mut width = 0;
+        for token in tokens {
+            if width > 0 && width + 1 + token.len() > LINE_WIDTH {
+                pgn.push('\n');
+                width = 0;
+            } else if width > 0 {
+                pgn.push(' ');
+                width += 1;
+            }
+            pgn.push_str(&token);
+            width += token.len();
+        }
+        pgn.push('\n');
+        pgn
+    }
+}
+
+/// Turn a line of moves, starting at `ply`, into movetext tokens
+fn write_line(tokens: &mut Vec<String>, line: &[PgnMove], mut ply: usize) {
+    // black's moves only need a number when something interrupts the flow of moves
+    let mut need_number = true;
+    for m in line {
+        if let Some(comment) = &m.comment_before {
+            comment_tokens(tokens, comment);
+        }
+        if ply.is_multiple_of(2) {
+            tokens.push(format!("{}.", ply / 2 + 1));
+        } else if need_number {
+            tokens.push(format!("{}...", ply / 2 + 1));
+        }
+        tokens.push(m.san.clone());
+        tokens.extend(m.nags.iter().map(|nag| format!("${}", nag)));
+        need_number = false;
+        if let Some(comment) = &m.comment {
+            comment_tokens(tokens, comment);
+            need_number = true;
+        }
+        for variation in m.variations.iter().filter(|v| !v.is_empty()) {
+            let start = tokens.len();
+            write_line(tokens, variation, ply);
+            tokens[start].insert(0, '(');
+            tokens.last_mut().unwrap().push(')');
+            need_number = true;
+        }
+        ply += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::{DrawReason, GameStatus};
+
+    const GAMES: &str = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[Date "1992.11.04"]
+[Round "29"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 {This opening is called the Ruy Lopez.} 3... a6
+4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 Nb8 10. d4 Nbd7
+11. c4 c6 12. cxb5 axb5 13. Nc3 Bb7 14. Bg5 b4 15. Nb1 h6 16. Bh4 c5 17. dxe5
+Nxe4 18. Bxe7 Qxe7 19. exd6 Qf6 20. Nbd2 Nxd6 21. Nc4 Nxc4 22. Bxc4 Nb6
+23. Ne5 Rae8 24. Bxf7+ Rxf7 25. Nxf7 Rxe1+ 26. Qxe1 Kxf7 27. Qe3 Qg5 28. Qxg5
+hxg5 29. b3 Ke6 30. a3 Kd6 31. axb4 cxb4 32. Ra5 Nd5 33. f3 Bc8 34. Kf2 Bf5
+35. Ra7 g6 36. Ra6+ Kc5 37. Ke1 Nf4 38. g3 Nxh3 39. Kd2 Kb5 40. Rd6 Kc5 41. Ra6
+Nf2 42. g4 Bd3 43. Re6 1/2-1/2
+
+[Event "Annotated"]
+[White "A"]
+[Black "B"]
+[Result "1-0"]
+[Opening "Scandinavian"]
+
+{A short game} 1. e4 d5!? 2. exd5 $1 Qxd5 (2... Nf6 3. c4 (3. Bb5+ Bd7)
+3... c6 ; the Icelandic
+) 3. Nc3 Qa5 4. d4 c6?! 5. Nf3 Bg4 6. Bf4 e6 7. h3 Bxf3 8. Qxf3 Bb4 9. Be2
+Nd7 10. a3 O-O-O?? 11. axb4 Qxa1+ 12. Kd2 Qxh1 13. Qxc6+ bxc6 14. Ba6# 1-0
+
+[Event "From a position"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 3 40"]
+
+40... Kd7 41. e4 *
+"#;
+
+    #[test]
+    fn parse_test() {
+        let games = parse(GAMES).unwrap();
+        assert_eq!(games.len(), 3);
+
+        let g = &games[0];
+        assert_eq!(g.tag("White"), Some("Fischer, Robert J."));
+        assert_eq!(g.result, PgnResult::Draw);
+        assert_eq!(g.moves.len(), 85);
+        assert_eq!(
+            g.moves[4].comment.as_deref(),
+            Some("This opening is called the Ruy Lopez.")
+        );
+        let replayed = g.replay().unwrap();
+        assert_eq!(
+            replayed.board().to_fen(),
+            "8/8/4R1p1/2k3p1/1p4P1/1P1b1P2/3K1n2/8 b - - 2 43"
+        );
+
+        let g = &games[1];
+        assert_eq!(g.tag("Opening"), Some("Scandinavian"));
+        assert_eq!(g.result, PgnResult::WhiteWins);
+        assert_eq!(g.moves[0].comment_before.as_deref(), Some("A short game"));
+        assert_eq!(g.moves[1].nags, vec![5]);
+        assert_eq!(g.moves[2].nags, vec![1]);
+        assert_eq!(g.moves[7].nags, vec![6]);
+        // 2... Nf6 with its own 3. Bb5+ sub variation
+        let variation = &g.moves[3].variations[0];
+        assert_eq!(variation[0].san, "Nf6");
+        assert_eq!(variation[1].variations[0][0].san, "Bb5+");
+        assert_eq!(variation[2].comment.as_deref(), Some("the Icelandic"));
+        let replayed = g.replay().unwrap();
+        assert_eq!(replayed.status(), GameStatus::Checkmate(Color::White));
+
+        let g = &games[2];
+        assert_eq!(g.result, PgnResult::Unknown);
+        assert_eq!(
+            g.replay().unwrap().board().to_fen(),
+            "8/3k4/8/8/4P3/8/8/4K3 b - e3 0 41"
+        );
+    }
+
+    #[test]
+    fn write_test() {
+        let games = parse(GAMES).unwrap();
+        let written = write(&games);
+        assert!(written.lines().all(|line| line.len() <= LINE_WIDTH));
+        let reread = parse(&written).unwrap();
+        assert_eq!(reread.len(), games.len());
+        for (a, b) in games.iter().zip(&reread) {
+            assert_eq!(a.moves, b.moves);
+            assert_eq!(a.result, b.result);
+            for (name, value) in &a.tags {
+                assert_eq!(b.tag(name), Some(value.as_str()));
+            }
+        }
+        // writing is stable once the tags have been put in order
+        assert_eq!(write(&reread), written);
+
+        let pgn = games[1].to_pgn();
+        assert!(pgn.starts_with("[Event \"Annotated\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n"));
+        assert!(pgn.contains("\n\n{A short game} 1. e4 d5 $5 2. exd5 $1 Qxd5 (2... Nf6 3. c4 (3. Bb5+ Bd7) 3... c6\n{the Icelandic}) 3. Nc3"));
+        assert!(games[2].to_pgn().ends_with("\n\n40... Kd7 41. e4 *\n"));
+    }
+
+    #[test]
+    fn repetition_from_pgn_test() {
+        let games = parse("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 1/2-1/2").unwrap();
+        assert_eq!(
+            games[0].replay().unwrap().status(),
+            GameStatus::Draw(DrawReason::ThreefoldRepetition)
+        );
+    }
+
+    #[test]
+    fn error_test() {
+        assert_eq!(parse("[Event \"x\"\n1. e4 *"), Err(PgnError::InvalidTag(1)));
+        assert_eq!(parse("[Event x]\n1. e4 *"), Err(PgnError::InvalidTag(1)));
+        assert_eq!(
+            parse("1. e4 {oops\n\n*"),
+            Err(PgnError::UnterminatedComment(1))
+        );
+        assert_eq!(
+            parse("1. e4\n(1. d4 *"),
+            Err(PgnError::UnterminatedVariation(2))
+        );
+        assert_eq!(parse("1. e4 ) *"), Err(PgnError::UnmatchedParen(1)));
+        assert_eq!(parse("\n$1 1. e4 *"), Err(PgnError::NothingToAnnotate(2)));
+        let games = parse("1. e4 e5 2. Ke3 *").unwrap();
+        assert_eq!(
+            games[0].replay().err(),
+            Some(PgnError::IllegalMove(SanError::Illegal("Ke3".into())))
+        );
+        let games = parse("1. e4 (1. d4 Kd7) e5 *").unwrap();
+        assert_eq!(
+            games[0].replay().err(),
+            Some(PgnError::IllegalMove(SanError::Illegal("Kd7".into())))
+        );
+    }
+}
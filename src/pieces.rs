@@ -1,8 +1,8 @@
 use std::fmt;
 
-const RESET_COLOR: &str = "\x1b[0m";
+pub(crate) const RESET_COLOR: &str = "\x1b[0m";
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum PieceType {
     Pawn,
     Rook,
@@ -14,38 +14,67 @@ pub enum PieceType {
 
 impl fmt::Display for PieceType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.letter())
+    }
+}
+
+impl PieceType {
+    /// The standard upper case letter used for this piece in FEN and algebraic notation
+    pub fn letter(self) -> char {
         use PieceType::*;
-        let s = match self {
-            Pawn => "p",
-            Rook => "R",
-            Knight => "K",
-            Bishop => "B",
-            Queen => "Q",
-            King => "K",
-        };
-        write!(f, "{}", s)
+        match self {
+            Pawn => 'P',
+            Rook => 'R',
+            Knight => 'N',
+            Bishop => 'B',
+            Queen => 'Q',
+            King => 'K',
+        }
+    }
+
+    pub fn from_letter(c: char) -> Option<Self> {
+        use PieceType::*;
+        Some(match c {
+            'P' => Pawn,
+            'R' => Rook,
+            'N' => Knight,
+            'B' => Bishop,
+            'Q' => Queen,
+            'K' => King,
+            _ => return None,
+        })
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Color {
     Black,
     White,
 }
 
+impl Color {
+    pub fn opposite(self) -> Self {
+        match self {
+            Color::Black => Color::White,
+            Color::White => Color::Black,
+        }
+    }
+}
+
+/// Emits the foreground color pieces of this color are drawn in,
+/// the square underneath is left to `render::RenderOptions` to color
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Color::*;
-        // TODO probably choose better background colors
         let s = match self {
-            Black => "\x1b[30;107m",
-            White => RESET_COLOR,
+            Black => "\x1b[1;30m",
+            White => "\x1b[1;97m",
         };
         write!(f, "{}", s)
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Piece {
     pub color: Color,
     pub typ: PieceType,
@@ -61,4 +90,23 @@ impl Piece {
     pub fn new(color: Color, typ: PieceType) -> Self {
         Self { color, typ }
     }
+
+    /// The Unicode chess symbol for this piece, outlined for white and filled for black
+    pub fn glyph(self) -> char {
+        use PieceType::*;
+        match (self.color, self.typ) {
+            (Color::White, King) => '♔',
+            (Color::White, Queen) => '♕',
+            (Color::White, Rook) => '♖',
+            (Color::White, Bishop) => '♗',
+            (Color::White, Knight) => '♘',
+            (Color::White, Pawn) => '♙',
+            (Color::Black, King) => '♚',
+            (Color::Black, Queen) => '♛',
+            (Color::Black, Rook) => '♜',
+            (Color::Black, Bishop) => '♝',
+            (Color::Black, Knight) => '♞',
+            (Color::Black, Pawn) => '♟',
+        }
+    }
 }
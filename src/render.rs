@@ -0,0 +1,234 @@
+use crate::pieces::*;
+use crate::Board;
+use std::{error, fmt, str::FromStr};
+
+const LIGHT_SQUARE: &str = "\x1b[48;5;180m";
+const DARK_SQUARE: &str = "\x1b[48;5;137m";
+
+/// How pieces are drawn
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum PieceStyle {
+    /// The standard letters K Q R B N P, in lower case for black when there are no colors
+    #[default]
+    Letters,
+    /// Unicode chess symbols like ♔ and ♚
+    Unicode,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseStyleError(String);
+
+impl fmt::Display for ParseStyleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown piece style '{}', expected letters or unicode",
+            self.0
+        )
+    }
+}
+
+impl error::Error for ParseStyleError {}
+
+impl FromStr for PieceStyle {
+    type Err = ParseStyleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "letters" | "ascii" => Ok(PieceStyle::Letters),
+            "unicode" => Ok(PieceStyle::Unicode),
+            _ => Err(ParseStyleError(s.to_string())),
+        }
+    }
+}
+
+/// How `Board::render` draws the board
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct RenderOptions {
+    /// Draw the board from black's side, with a1 in the top right
+    pub flipped: bool,
+    /// Use ANSI colors for the squares and pieces, otherwise only the pieces stand out
+    pub color: bool,
+    pub style: PieceStyle,
+}
+
+impl Board {
+    /// Draw the board with rank and file labels around it
+    pub fn render(&self, options: RenderOptions) -> String {
+        let order = |flip: bool| -> Vec<usize> {
+            if flip {
+                (0..8).rev().collect()
+            } else {
+                (0..8).collect()
+            }
+        };
+        let files = order(options.flipped);
+        let ranks = order(!options.flipped);
+
+        let mut labels = String::from("  ");
+        for &x in &files {
+            let file = (b'a' + x as u8) as char;
+            if options.color {
+                labels.push_str(&format!(" {} ", file));
+            } else {
+                labels.push_str(&format!(" {}", file));
+            }
+        }
+
+        let mut out = String::new();
+        out.push_str(labels.trim_end());
+        out.push('\n');
+        for &y in &ranks {
+            out.push_str(&format!("{} ", y + 1));
+            for &x in &files {
+                let piece = self.board[y][x];
+                let symbol = |p: Piece| match options.style {
+                    PieceStyle::Unicode => p.glyph(),
+                    // colors already tell the sides apart
+                    PieceStyle::Letters if options.color || p.color == Color::White => {
+                        p.typ.letter()
+                    }
+                    PieceStyle::Letters => p.typ.letter().to_ascii_lowercase(),
+                };
+                if options.color {
+                    // a1 is a dark square
+                    let bg = if (x + y) % 2 == 0 {
+                        DARK_SQUARE
+                    } else {
+                        LIGHT_SQUARE
+                    };
+                    match piece {
+                        Some(p) => out.push_str(&format!(
+                            "{} {}{} {}",
+                            bg,
+                            p.color,
+                            symbol(p),
+                            RESET_COLOR
+                        )),
+                        None => out.push_str(&format!("{}   {}", bg, RESET_COLOR)),
+                    }
+                } else {
+                    out.push(' ');
+                    out.push(piece.map_or('.', symbol));
+                }
+            }
+            out.push_str(&format!(" {}\n", y + 1));
+        }
+        out.push_str(labels.trim_end());
+        out.push('\n');
+        out
+    }
+}
+
+/// Draws the board in plain ASCII letters, see `Board::render` for other styles
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(RenderOptions::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_render_test() {
+        let b = Board::new();
+        assert_eq!(
+            b.to_string(),
+            "   a b c d e f g h
+8  r n b q k b n r 8
+7  p p p p p p p p 7
+6  . . . . . . . . 6
+5  . . . . . . . . 5
+4  . . . . . . . . 4
+3  . . . . . . . . 3
+2  P P P P P P P P 2
+1  R N B Q K B N R 1
+   a b c d e f g h
+"
+        );
+        let b = Board::from_fen("4k3/8/8/8/4P3/8/8/R3K3 b - e3 0 1").unwrap();
+        assert_eq!(
+            b.render(RenderOptions {
+                flipped: true,
+                ..Default::default()
+            }),
+            "   h g f e d c b a
+1  . . . K . . . R 1
+2  . . . . . . . . 2
+3  . . . . . . . . 3
+4  . . . P . . . . 4
+5  . . . . . . . . 5
+6  . . . . . . . . 6
+7  . . . . . . . . 7
+8  . . . k . . . . 8
+   h g f e d c b a
+"
+        );
+    }
+
+    #[test]
+    fn unicode_render_test() {
+        let b = Board::new();
+        let unicode = RenderOptions {
+            style: PieceStyle::Unicode,
+            ..Default::default()
+        };
+        let out = b.render(unicode);
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines[1], "8  ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜ 8");
+        assert_eq!(lines[2], "7  ♟ ♟ ♟ ♟ ♟ ♟ ♟ ♟ 7");
+        assert_eq!(lines[7], "2  ♙ ♙ ♙ ♙ ♙ ♙ ♙ ♙ 2");
+        assert_eq!(lines[8], "1  ♖ ♘ ♗ ♕ ♔ ♗ ♘ ♖ 1");
+        let out = b.render(RenderOptions {
+            color: true,
+            ..unicode
+        });
+        assert!(out.contains(&format!("{}♘ ", Color::White)));
+        assert!(out.contains(&format!("{}♞ ", Color::Black)));
+    }
+
+    #[test]
+    fn piece_style_test() {
+        assert_eq!("unicode".parse(), Ok(PieceStyle::Unicode));
+        assert_eq!("Letters".parse(), Ok(PieceStyle::Letters));
+        assert_eq!(
+            "fancy".parse::<PieceStyle>(),
+            Err(ParseStyleError("fancy".into()))
+        );
+        // the knight no longer looks like the king
+        assert_eq!(PieceType::Knight.to_string(), "N");
+        assert_eq!(PieceType::King.to_string(), "K");
+        assert_eq!(PieceType::Pawn.to_string(), "P");
+    }
+
+    #[test]
+    fn color_render_test() {
+        let b = Board::from_fen("7k/8/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let out = b.render(RenderOptions {
+            color: true,
+            ..Default::default()
+        });
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines[0], "   a  b  c  d  e  f  g  h");
+        // h8 is a dark square holding the black king
+        assert!(lines[1].starts_with(&format!("8 {}   {}", LIGHT_SQUARE, RESET_COLOR)));
+        assert!(lines[1].ends_with(&format!(
+            "{} {}K {} 8",
+            DARK_SQUARE,
+            Color::Black,
+            RESET_COLOR
+        )));
+        // a1 is a dark square holding the white king
+        assert!(lines[8].starts_with(&format!(
+            "1 {} {}K {}",
+            DARK_SQUARE,
+            Color::White,
+            RESET_COLOR
+        )));
+        assert_eq!(lines[8].matches(DARK_SQUARE).count(), 4);
+        assert_eq!(lines[8].matches(LIGHT_SQUARE).count(), 4);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::bitboard::square;
+use crate::moves::*;
+use crate::pieces::*;
+use crate::square::Square;
+use crate::status::GameStatus;
+use crate::{Board, Position};
+use std::{error, fmt};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    /// The text is not shaped like a move at all
+    Invalid(String),
+    /// No legal move matches
+    Illegal(String),
+    /// More than one legal move matches
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Invalid(s) => write!(f, "'{}' is not a move", s),
+            SanError::Illegal(s) => write!(f, "'{}' is not a legal move here", s),
+            SanError::Ambiguous(s) => write!(f, "'{}' could mean more than one move", s),
+        }
+    }
+}
+
+impl error::Error for SanError {}
+
+fn file_letter(x: usize) -> char {
+    (b'a' + x as u8) as char
+}
+
+impl Board {
+    /// Write a legal move for the side to move in Standard Algebraic Notation
+    pub fn to_san(&self, m: Move) -> String {
+        let p = self.piece_at(m.from).expect("a move starts on a piece");
+        let flags = self.move_flags(m.from, m.to);
+        let mut san = String::new();
+        if flags.castle {
+            san.push_str(if m.to.0 == 6 { "O-O" } else { "O-O-O" });
+        } else if p.typ == PieceType::Pawn {
+            if flags.capture {
+                san.push(file_letter(m.from.0));
+                san.push('x');
+            }
+            san.push_str(&square(m.to).to_string());
+            if let Some(typ) = m.promotion {
+                san.push('=');
+                san.push(typ.letter());
+            }
+        } else {
+            san.push(p.typ.letter());
+            // only name as much of the starting square as needed to tell apart
+            // other pieces of the same kind that could also move there
+            let others = self
+                .legal_moves(p.color)
+                .into_iter()
+                .filter(|o| o.to == m.to && o.from != m.from && self.piece_at(o.from) == Some(p))
+                .collect::<Vec<_>>();
+            if !others.is_empty() {
+                if others.iter().all(|o| o.from.0 != m.from.0) {
+                    san.push(file_letter(m.from.0));
+                } else if others.iter().all(|o| o.from.1 != m.from.1) {
+                    san.push_str(&(m.from.1 + 1).to_string());
+                } else {
+                    san.push_str(&square(m.from).to_string());
+                }
+            }
+            if flags.capture {
+                san.push('x');
+            }
+            san.push_str(&square(m.to).to_string());
+        }
+        let after = self.test_move(m);
+        if let GameStatus::Checkmate(_) = after.status() {
+            san.push('#');
+        } else if after.in_check(after.turn) {
+            san.push('+');
+        }
+        san
+    }
+
+    /// Read a move for the side to move written in Standard Algebraic Notation.
+    /// Check and annotation marks are accepted but not required.
+    pub fn parse_san(&self, s: &str) -> Result<Move, SanError> {
+        let invalid = || SanError::Invalid(s.to_string());
+        let mut text = s.trim();
+        text = text.strip_suffix("e.p.").unwrap_or(text).trim_end();
+        text = text.trim_end_matches(['+', '#', '!', '?']);
+        let moves = self.legal_moves(self.turn);
+        let found = if let Some(side) = ["O-O-O", "0-0-0", "O-O", "0-0"]
+            .iter()
+            .position(|&c| c == text)
+        {
+            let file = if side < 2 { 2 } else { 6 };
+            moves
+                .into_iter()
+                .filter(|m| m.flags.castle && m.to.0 == file)
+                .collect::<Vec<_>>()
+        } else {
+            let (typ, rest) = match text.chars().next() {
+                Some(c @ ('N' | 'B' | 'R' | 'Q' | 'K')) => {
+                    (PieceType::from_letter(c).unwrap(), &text[1..])
+                }
+                _ => (PieceType::Pawn, text),
+            };
+            let (rest, promotion) = match rest.char_indices().last() {
+                Some((i, c @ ('N' | 'B' | 'R' | 'Q'))) => (
+                    rest[..i].strip_suffix('=').unwrap_or(&rest[..i]),
+                    PieceType::from_letter(c),
+                ),
+                _ => (rest, None),
+            };
+            if rest.len() < 2 || !rest.is_ascii() {
+                return Err(invalid());
+            }
+            let (from, to) = rest.split_at(rest.len() - 2);
+            let to = Position::from(to.parse::<Square>().map_err(|_| invalid())?);
+            let from = from.strip_suffix('x').unwrap_or(from);
+            // whatever is left is some or all of the starting square
+            let (mut file, mut rank) = (None, None);
+            for c in from.chars() {
+                match c {
+                    'a'..='h' if file.is_none() && rank.is_none() => {
+                        file = Some(c as usize - 'a' as usize)
+                    }
+                    '1'..='8' if rank.is_none() => rank = Some(c as usize - '1' as usize),
+                    _ => return Err(invalid()),
+                }
+            }
+            moves
+                .into_iter()
+                .filter(|m| {
+                    m.to == to
+                        && m.promotion == promotion
+                        && self.piece_at(m.from).map(|p| p.typ) == Some(typ)
+                        && file.is_none_or(|x| x == m.from.0)
+                        && rank.is_none_or(|y| y == m.from.1)
+                })
+                .collect()
+        };
+        match found[..] {
+            [m] => Ok(m),
+            [] => Err(SanError::Illegal(s.to_string())),
+            _ => Err(SanError::Ambiguous(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mv(board: &Board, from: &str, to: &str) -> Move {
+        let (from, to) = (
+            from.parse::<Square>().unwrap(),
+            to.parse::<Square>().unwrap(),
+        );
+        *board
+            .legal_moves(board.turn())
+            .iter()
+            .find(|m| m.from == from.into() && m.to == to.into())
+            .unwrap()
+    }
+
+    #[test]
+    fn disambiguation_test() {
+        let b = Board::from_fen("rnbqkb1r/ppp2ppp/4pn2/3p4/2PP4/2N5/PP2PPPP/R1BQKBNR b KQkq - 1 4")
+            .unwrap();
+        assert_eq!(b.to_san(mv(&b, "b8", "d7")), "Nbd7");
+        assert_eq!(b.to_san(mv(&b, "f6", "d7")), "Nfd7");
+        assert_eq!(b.to_san(mv(&b, "f6", "e4")), "Ne4");
+        assert_eq!(b.to_san(mv(&b, "d5", "c4")), "dxc4");
+        assert_eq!(b.parse_san("Nbd7"), Ok(mv(&b, "b8", "d7")));
+        assert_eq!(b.parse_san("N8d7"), Ok(mv(&b, "b8", "d7")));
+        assert_eq!(b.parse_san("dxc4"), Ok(mv(&b, "d5", "c4")));
+        assert_eq!(b.parse_san("Nd7"), Err(SanError::Ambiguous("Nd7".into())));
+        assert_eq!(b.parse_san("Nd5"), Err(SanError::Illegal("Nd5".into())));
+        assert_eq!(b.parse_san("Zd7"), Err(SanError::Invalid("Zd7".into())));
+
+        let b = Board::from_fen("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert_eq!(b.to_san(mv(&b, "a1", "a3")), "R1a3");
+        assert_eq!(b.parse_san("R5a3"), Ok(mv(&b, "a5", "a3")));
+
+        let b = Board::from_fen("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1").unwrap();
+        assert_eq!(b.to_san(mv(&b, "a1", "b2")), "Qa1b2");
+        assert_eq!(b.to_san(mv(&b, "c1", "b2")), "Qcb2");
+        assert_eq!(b.parse_san("Qa1b2"), Ok(mv(&b, "a1", "b2")));
+        assert_eq!(b.parse_san("Qab2"), Err(SanError::Ambiguous("Qab2".into())));
+    }
+
+    #[test]
+    fn special_moves_test() {
+        let b = Board::from_fen("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3")
+            .unwrap();
+        let m = mv(&b, "e5", "d6");
+        assert!(m.flags.en_passant);
+        assert_eq!(b.to_san(m), "exd6");
+        assert_eq!(b.parse_san("exd6 e.p."), Ok(m));
+        assert_eq!(b.parse_san("exd6e.p."), Ok(m));
+
+        let b = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(b.to_san(mv(&b, "e1", "c1")), "O-O-O");
+        assert_eq!(b.to_san(mv(&b, "e1", "g1")), "O-O");
+        assert_eq!(b.parse_san("O-O-O"), Ok(mv(&b, "e1", "c1")));
+        assert_eq!(b.parse_san("0-0"), Ok(mv(&b, "e1", "g1")));
+
+        let b = Board::from_fen("8/4P3/8/8/k7/8/8/4K3 w - - 0 1").unwrap();
+        let queen = Move {
+            promotion: Some(PieceType::Queen),
+            ..Move::new((4, 6), (4, 7))
+        };
+        assert_eq!(b.to_san(queen), "e8=Q+");
+        assert_eq!(
+            b.to_san(Move {
+                promotion: Some(PieceType::Knight),
+                ..queen
+            }),
+            "e8=N"
+        );
+        assert_eq!(b.parse_san("e8=Q+"), Ok(queen));
+        assert_eq!(b.parse_san("e8Q"), Ok(queen));
+        assert_eq!(b.parse_san("e8"), Err(SanError::Illegal("e8".into())));
+
+        let b = Board::from_fen("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2")
+            .unwrap();
+        assert_eq!(b.to_san(mv(&b, "d8", "h4")), "Qh4#");
+        assert_eq!(b.parse_san("Qh4#"), Ok(mv(&b, "d8", "h4")));
+        assert_eq!(b.parse_san("Qh4"), Ok(mv(&b, "d8", "h4")));
+    }
+
+    #[test]
+    fn san_round_trip_test() {
+        let b =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        for m in b.legal_moves(b.turn()) {
+            assert_eq!(b.parse_san(&b.to_san(m)), Ok(m));
+        }
+    }
+}
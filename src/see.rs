@@ -0,0 +1,132 @@
+use crate::bitboard::{square, Bitboard};
+use crate::eval::piece_value;
+use crate::moves::Move;
+use crate::pieces::*;
+use crate::Board;
+
+/// Cheapest pieces first, the order captures are made in an exchange
+const CHEAPEST_FIRST: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+impl Board {
+    /// Static exchange evaluation: the material the side to move wins or loses if
+    /// `m` starts a run of captures on its target square, each side taking back with
+    /// its cheapest piece for as long as that pays. Pins are not looked at.
+    pub fn see(&self, m: Move) -> i32 {
+        let Some(moved) = self.piece_at(m.from) else {
+            return 0;
+        };
+        let bb = self.bitboards();
+        let target = square(m.to);
+        let mut occupied = bb.occupied() ^ square(m.from).into();
+        let victim = if m.flags.en_passant {
+            occupied ^= square((m.to.0, m.from.1)).into();
+            Some(PieceType::Pawn)
+        } else {
+            self.piece_at(m.to).map(|p| p.typ)
+        };
+
+        // gains[i] is what the side making capture i has won once it is made,
+        // if the other side stops there
+        let mut gains = vec![victim.map_or(0, piece_value)];
+        let mut on_target = moved.typ;
+        if let Some(typ) = m.promotion {
+            gains[0] += piece_value(typ) - piece_value(PieceType::Pawn);
+            on_target = typ;
+        }
+        let mut side = moved.color.opposite();
+        loop {
+            let attackers = bb.attackers_with(target, occupied);
+            let Some((typ, from)) = CHEAPEST_FIRST.into_iter().find_map(|typ| {
+                let set = attackers & bb.pieces(side, typ);
+                set.first().map(|sq| (typ, sq))
+            }) else {
+                break;
+            };
+            // the king may only take last, onto a square nothing else attacks,
+            // counting sliders it stood in the way of until it moved
+            if typ == PieceType::King {
+                let after = bb.attackers_with(target, occupied ^ Bitboard::from(from));
+                if !(after & bb.color(side.opposite())).is_empty() {
+                    break;
+                }
+            }
+            gains.push(piece_value(on_target) - gains.last().expect("gains starts non-empty"));
+            occupied ^= Bitboard::from(from);
+            on_target = typ;
+            side = side.opposite();
+        }
+
+        // each side may decline to take back when that would lose more
+        while gains.len() > 1 {
+            let last = gains.pop().expect("more than one gain");
+            let prev = gains.last_mut().expect("more than one gain");
+            *prev = -(-*prev).max(last);
+        }
+        gains[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::MoveFlags;
+
+    fn see(fen: &str, m: &str) -> i32 {
+        let b = Board::from_fen(fen).unwrap();
+        b.see(b.parse_uci(m).unwrap())
+    }
+
+    #[test]
+    fn see_test() {
+        // an undefended pawn
+        assert_eq!(
+            see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"),
+            100
+        );
+        // a pawn defended by a knight, with pieces lined up on both sides
+        assert_eq!(
+            see(
+                "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1",
+                "d3e5"
+            ),
+            100 - 320
+        );
+        // the rook behind the queen takes back the rook that took the queen
+        assert_eq!(
+            see("4k3/8/4r3/8/4p3/8/4Q3/4R1K1 w - - 0 1", "e2e4"),
+            100 - 900 + 500
+        );
+        assert_eq!(
+            see("4k3/8/4r3/8/4p3/8/4Q3/6K1 w - - 0 1", "e2e4"),
+            100 - 900
+        );
+        // black would lose its rook to the rook behind the pawn, so it does not take back
+        assert_eq!(see("4k3/4r3/8/8/4p3/3P4/8/4R1K1 w - - 0 1", "d3e4"), 100);
+        // a quiet move onto an attacked square loses the piece
+        assert_eq!(see("4k3/8/8/3p4/8/2N5/8/4K3 w - - 0 1", "c3e4"), -320);
+        assert_eq!(see("4k3/8/8/3p4/8/2N5/8/4K3 w - - 0 1", "c3b5"), 0);
+        // en passant, then the pawn is taken back
+        assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 100);
+        assert_eq!(see("4k3/2p5/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 0);
+        // the king cannot take back a defended piece
+        assert_eq!(see("8/8/8/4k3/4p3/8/5N2/4RK2 w - - 0 1", "e1e4"), 100);
+        assert_eq!(see("8/8/8/4k3/4p3/8/8/4RK2 w - - 0 1", "e1e4"), 100 - 500);
+        // nor step out of the way of a rook that then sees the square
+        let b = Board::from_fen("k7/8/3p4/4p3/4K3/5N2/8/4r3 w - - 0 1").unwrap();
+        let nxe5 = Move {
+            flags: MoveFlags {
+                capture: true,
+                ..Default::default()
+            },
+            ..Move::new((5, 2), (4, 4))
+        };
+        assert_eq!(b.see(nxe5), 100 - 320);
+    }
+}
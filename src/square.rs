@@ -0,0 +1,133 @@
+use crate::Position;
+use std::{error, fmt, str::FromStr};
+
+/// A square on the board. Can only be made from in bounds coordinates so it never needs checking.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Square(u8);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SquareError {
+    /// Not a file from a to h followed by a rank from 1 to 8
+    InvalidName(String),
+    OutOfBounds(Position),
+}
+
+impl fmt::Display for SquareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SquareError::InvalidName(s) => write!(f, "'{}' is not a square name like e4", s),
+            SquareError::OutOfBounds(pos) => write!(f, "{:?} is not on the board", pos),
+        }
+    }
+}
+
+impl error::Error for SquareError {}
+
+impl Square {
+    /// `file` and `rank` both count from 0, so a1 is `(0, 0)` and h8 is `(7, 7)`
+    pub fn new(file: usize, rank: usize) -> Option<Self> {
+        if file < 8 && rank < 8 {
+            Some(Self((rank * 8 + file) as u8))
+        } else {
+            None
+        }
+    }
+
+    /// Index from 0 for a1 to 63 for h8, going along each rank in turn
+    pub fn from_index(index: usize) -> Option<Self> {
+        (index < 64).then_some(Self(index as u8))
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn file(self) -> usize {
+        self.index() % 8
+    }
+
+    pub fn rank(self) -> usize {
+        self.index() / 8
+    }
+
+    /// Every square from a1 to h8
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..64).map(Self)
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            (b'a' + self.file() as u8) as char,
+            self.rank() + 1
+        )
+    }
+}
+
+impl FromStr for Square {
+    type Err = SquareError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] => {
+                Ok(Self((rank - b'1') * 8 + (file - b'a')))
+            }
+            _ => Err(SquareError::InvalidName(s.to_string())),
+        }
+    }
+}
+
+impl From<Square> for Position {
+    fn from(sq: Square) -> Self {
+        (sq.file(), sq.rank())
+    }
+}
+
+impl TryFrom<Position> for Square {
+    type Error = SquareError;
+
+    fn try_from(pos: Position) -> Result<Self, Self::Error> {
+        Self::new(pos.0, pos.1).ok_or(SquareError::OutOfBounds(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_name_test() {
+        assert_eq!("a1".parse(), Ok(Square::new(0, 0).unwrap()));
+        assert_eq!("e4".parse(), Ok(Square::new(4, 3).unwrap()));
+        assert_eq!("h8".parse(), Ok(Square::new(7, 7).unwrap()));
+        for sq in Square::all() {
+            assert_eq!(sq.to_string().parse(), Ok(sq));
+        }
+        for s in ["", "e", "e0", "e9", "i1", "E4", "e44", "4e"] {
+            assert_eq!(
+                s.parse::<Square>(),
+                Err(SquareError::InvalidName(s.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn square_bounds_test() {
+        assert_eq!(Square::all().count(), 64);
+        assert_eq!(Square::new(8, 0), None);
+        assert_eq!(Square::new(0, 8), None);
+        assert_eq!(Square::new(usize::MAX, usize::MAX), None);
+        assert_eq!(Square::from_index(64), None);
+        let sq = Square::from_index(12).unwrap();
+        assert_eq!(sq.to_string(), "e2");
+        assert_eq!(Position::from(sq), (4, 1));
+        assert_eq!(Square::try_from((4, 1)), Ok(sq));
+        assert_eq!(
+            Square::try_from((4, 8)),
+            Err(SquareError::OutOfBounds((4, 8)))
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use crate::moves::Move;
+use crate::pieces::*;
+use crate::{Board, Position};
+use std::fmt;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DrawReason {
+    /// A hundred plies without a capture or a pawn move
+    FiftyMoveRule,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    /// Neither side has enough pieces left to ever checkmate
+    InsufficientMaterial,
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DrawReason::*;
+        let s = match self {
+            FiftyMoveRule => "the fifty move rule",
+            ThreefoldRepetition => "threefold repetition",
+            FivefoldRepetition => "fivefold repetition",
+            InsufficientMaterial => "insufficient material",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameStatus {
+    InProgress,
+    /// The winning color
+    Checkmate(Color),
+    Stalemate,
+    Draw(DrawReason),
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use GameStatus::*;
+        match self {
+            InProgress => write!(f, "game in progress"),
+            Checkmate(Color::White) => write!(f, "checkmate, white wins"),
+            Checkmate(Color::Black) => write!(f, "checkmate, black wins"),
+            Stalemate => write!(f, "stalemate, the game is drawn"),
+            Draw(reason) => write!(f, "draw by {}", reason),
+        }
+    }
+}
+
+impl Board {
+    /// Check if `color` has at least one legal move, stopping at the first one found
+    pub fn has_legal_move(&self, color: Color) -> bool {
+        let bb = self.bitboards();
+        bb.color(color).any(|from| {
+            let piece = self
+                .piece_at(from)
+                .expect("the square is in the color's set");
+            bb.destinations(self, from, piece).any(|to| {
+                let (a, b) = (Position::from(from), Position::from(to));
+                let m = Move {
+                    flags: self.move_flags(a, b),
+                    ..Move::new(a, b)
+                };
+                !bb.after(m, piece).in_check(color)
+            })
+        })
+    }
+
+    /// Check if neither side can possibly checkmate: a lone king against a king with at most
+    /// one minor piece, or any number of bishops that all stand on the same colored squares
+    pub fn insufficient_material(&self) -> bool {
+        let mut knights = 0;
+        // bishops counted by the color of the square they stand on
+        let mut bishops = [0; 2];
+        for y in 0..8 {
+            for x in 0..8 {
+                match self.board[y][x].map(|p| p.typ) {
+                    None | Some(PieceType::King) => {}
+                    Some(PieceType::Knight) => knights += 1,
+                    Some(PieceType::Bishop) => bishops[(x + y) % 2] += 1,
+                    Some(_) => return false,
+                }
+            }
+        }
+        match (knights, bishops[0] + bishops[1]) {
+            (0, 0) | (1, 0) | (0, 1) => true,
+            (0, _) => bishops[0] == 0 || bishops[1] == 0,
+            _ => false,
+        }
+    }
+
+    /// Work out whether the game is over from the point of view of the side to move
+    pub fn status(&self) -> GameStatus {
+        if !self.has_legal_move(self.turn) {
+            if self.in_check(self.turn) {
+                GameStatus::Checkmate(self.turn.opposite())
+            } else {
+                GameStatus::Stalemate
+            }
+        } else if self.halfmove_clock >= 100 {
+            GameStatus::Draw(DrawReason::FiftyMoveRule)
+        } else if self.insufficient_material() {
+            GameStatus::Draw(DrawReason::InsufficientMaterial)
+        } else {
+            GameStatus::InProgress
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checkmate_test() {
+        let mut b = Board::new();
+        assert_eq!(b.status(), GameStatus::InProgress);
+        // fool's mate
+        for (a, c) in [((5, 1), (5, 2)), ((4, 6), (4, 4)), ((6, 1), (6, 3))] {
+            b.play(Move::new(a, c)).unwrap();
+            assert_eq!(b.status(), GameStatus::InProgress);
+        }
+        b.play(Move::new((3, 7), (7, 3))).unwrap();
+        assert_eq!(b.status(), GameStatus::Checkmate(Color::Black));
+        assert!(!b.has_legal_move(Color::White));
+        assert!(b.has_legal_move(Color::Black));
+    }
+
+    #[test]
+    fn stalemate_test() {
+        let b = Board::from_squares([
+            [
+                Some(Piece::new(Color::White, PieceType::King)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+            [None; 8],
+            [
+                None,
+                Some(Piece::new(Color::Black, PieceType::Queen)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [None; 8],
+            [
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(Piece::new(Color::Black, PieceType::King)),
+            ],
+        ]);
+        assert!(!b.in_check(Color::White));
+        assert_eq!(b.status(), GameStatus::Stalemate);
+    }
+
+    #[test]
+    fn has_legal_move_test() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            // mated, stalemated, and only a king move or en passant to get out of trouble
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+            "7k/5Q2/8/8/8/8/8/K7 b - - 0 1",
+            "8/8/8/8/k1pP4/8/8/K1Q5 b - d3 0 1",
+        ] {
+            let b = Board::from_fen(fen).unwrap();
+            for color in [Color::White, Color::Black] {
+                assert_eq!(
+                    b.has_legal_move(color),
+                    !b.legal_moves(color).is_empty(),
+                    "{}",
+                    fen
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn insufficient_material_test() {
+        let mut b = Board::default();
+        b.set_square((4, 0), Some(Piece::new(Color::White, PieceType::King)));
+        b.set_square((4, 7), Some(Piece::new(Color::Black, PieceType::King)));
+        assert!(b.insufficient_material()); // K v K
+        assert_eq!(
+            b.status(),
+            GameStatus::Draw(DrawReason::InsufficientMaterial)
+        );
+        b.set_square((1, 0), Some(Piece::new(Color::White, PieceType::Knight)));
+        assert!(b.insufficient_material()); // K+N v K
+        b.set_square((6, 7), Some(Piece::new(Color::Black, PieceType::Knight)));
+        assert!(!b.insufficient_material()); // K+N v K+N can still mate
+        b.set_square((1, 0), None);
+        b.set_square((6, 7), None);
+        b.set_square((2, 0), Some(Piece::new(Color::White, PieceType::Bishop)));
+        assert!(b.insufficient_material()); // K+B v K
+        b.set_square((5, 7), Some(Piece::new(Color::Black, PieceType::Bishop)));
+        assert!(b.insufficient_material()); // bishops on the same color
+        b.set_square((5, 5), Some(Piece::new(Color::White, PieceType::Bishop)));
+        assert!(b.insufficient_material());
+        b.set_square((5, 7), None);
+        b.set_square((2, 7), Some(Piece::new(Color::Black, PieceType::Bishop)));
+        assert!(!b.insufficient_material()); // bishops on opposite colors
+        b.set_square((2, 7), None);
+        b.set_square((3, 3), Some(Piece::new(Color::Black, PieceType::Pawn)));
+        assert!(!b.insufficient_material());
+        assert!(!Board::new().insufficient_material());
+    }
+
+    #[test]
+    fn fifty_move_test() {
+        let mut b = Board::new();
+        b.halfmove_clock = 99;
+        assert_eq!(b.status(), GameStatus::InProgress);
+        b.play(Move::new((6, 0), (5, 2))).unwrap();
+        assert_eq!(b.status(), GameStatus::Draw(DrawReason::FiftyMoveRule));
+    }
+}
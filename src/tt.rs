@@ -0,0 +1,232 @@
+use crate::engine::{is_mate_score, MAX_DEPTH};
+use crate::moves::Move;
+use std::mem;
+
+/// Size of the table when none is asked for, in megabytes
+pub const DEFAULT_HASH_MB: usize = 16;
+
+/// How a stored score relates to the true score of the position
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// The search failed high, so the true score is at least this
+    Lower,
+    /// The search failed low, so the true score is at most this
+    Upper,
+}
+
+/// What an earlier search found out about a position
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub hash: u64,
+    pub depth: u32,
+    pub bound: Bound,
+    pub score: i32,
+    pub best_move: Option<Move>,
+    /// Which search stored it, so old entries make way for new ones
+    generation: u8,
+}
+
+/// A fixed size cache of search results keyed by `Board::hash`
+#[derive(Debug, Clone)]
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    generation: u8,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(DEFAULT_HASH_MB)
+    }
+}
+
+/// Mate scores count plies from the root, but a position can be reached at
+/// any ply, so they are stored counting from the position itself
+fn score_to_tt(score: i32, ply: u32) -> i32 {
+    match score {
+        s if is_mate_score(s) && s > 0 => s + ply as i32,
+        s if is_mate_score(s) => s - ply as i32,
+        s => s,
+    }
+}
+
+fn score_from_tt(score: i32, ply: u32) -> i32 {
+    match score {
+        s if is_mate_score(s) && s > 0 => s - ply as i32,
+        s if is_mate_score(s) => s + ply as i32,
+        s => s,
+    }
+}
+
+impl TranspositionTable {
+    /// A table taking up about `megabytes` of memory. Zero makes a table that stores nothing.
+    pub fn new(megabytes: usize) -> Self {
+        let fit = (megabytes << 20) / mem::size_of::<Option<Entry>>();
+        // a power of two so the hash can be masked down to an index
+        let len = if fit == 0 { 0 } else { 1 << fit.ilog2() };
+        Self {
+            entries: vec![None; len],
+            generation: 0,
+        }
+    }
+
+    /// How many positions fit in the table
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Forget everything, as between games
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.generation = 0;
+    }
+
+    /// Mark entries stored so far as old, so the next search can replace them first
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn index(&self, hash: u64) -> Option<usize> {
+        (!self.entries.is_empty()).then(|| hash as usize & (self.entries.len() - 1))
+    }
+
+    /// Look up the position with `hash`, found `ply` plies from the root
+    pub fn probe(&self, hash: u64, ply: u32) -> Option<Entry> {
+        let entry = self.entries[self.index(hash)?].filter(|e| e.hash == hash)?;
+        Some(Entry {
+            score: score_from_tt(entry.score, ply),
+            ..entry
+        })
+    }
+
+    /// Remember a search result. Deeper results from the current search are kept
+    /// over shallower ones for other positions, anything else is replaced.
+    pub fn store(
+        &mut self,
+        hash: u64,
+        depth: u32,
+        bound: Bound,
+        score: i32,
+        best_move: Option<Move>,
+        ply: u32,
+    ) {
+        let Some(i) = self.index(hash) else {
+            return;
+        };
+        let mut best_move = best_move;
+        if let Some(old) = self.entries[i] {
+            if old.hash == hash {
+                // a search that failed low has no best move, so keep the one found before
+                best_move = best_move.or(old.best_move);
+            } else if old.generation == self.generation && old.depth > depth {
+                return;
+            }
+        }
+        debug_assert!(depth <= MAX_DEPTH);
+        self.entries[i] = Some(Entry {
+            hash,
+            depth,
+            bound,
+            score: score_to_tt(score, ply),
+            best_move,
+            generation: self.generation,
+        });
+    }
+
+    /// How full the table is in parts per thousand, from a sample of entries
+    /// stored by the current search, as UCI reports it
+    pub fn hashfull(&self) -> u32 {
+        let sample = self.entries.len().min(1000);
+        if sample == 0 {
+            return 0;
+        }
+        let used = self.entries[..sample]
+            .iter()
+            .flatten()
+            .filter(|e| e.generation == self.generation)
+            .count();
+        (used * 1000 / sample) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::MATE;
+
+    #[test]
+    fn store_probe_test() {
+        let mut tt = TranspositionTable::new(1);
+        assert!(tt.capacity().is_power_of_two());
+        assert!(tt.capacity() * mem::size_of::<Option<Entry>>() <= 1 << 20);
+        let m = Move::new((4, 1), (4, 3));
+        assert_eq!(tt.probe(42, 0), None);
+        tt.store(42, 3, Bound::Lower, 25, Some(m), 2);
+        let e = tt.probe(42, 5).unwrap();
+        assert_eq!(
+            (e.depth, e.bound, e.score, e.best_move),
+            (3, Bound::Lower, 25, Some(m))
+        );
+        // same slot, different position
+        assert_eq!(tt.probe(42 + tt.capacity() as u64, 0), None);
+
+        tt.clear();
+        assert_eq!(tt.probe(42, 0), None);
+
+        let mut off = TranspositionTable::new(0);
+        assert_eq!(off.capacity(), 0);
+        off.store(42, 3, Bound::Exact, 0, None, 0);
+        assert_eq!(off.probe(42, 0), None);
+        assert_eq!(off.hashfull(), 0);
+    }
+
+    #[test]
+    fn mate_score_test() {
+        let mut tt = TranspositionTable::new(1);
+        // mate in three plies from a position found four plies into the search
+        tt.store(7, 5, Bound::Exact, MATE - 7, None, 4);
+        assert_eq!(tt.probe(7, 4).unwrap().score, MATE - 7);
+        // reached sooner, the mate is sooner too
+        assert_eq!(tt.probe(7, 2).unwrap().score, MATE - 5);
+        tt.store(8, 5, Bound::Upper, -(MATE - 6), None, 4);
+        assert_eq!(tt.probe(8, 1).unwrap().score, -(MATE - 3));
+        tt.store(9, 5, Bound::Exact, -150, None, 4);
+        assert_eq!(tt.probe(9, 1).unwrap().score, -150);
+    }
+
+    #[test]
+    fn replacement_test() {
+        let mut tt = TranspositionTable::new(1);
+        let other = 1 + tt.capacity() as u64;
+        let m = Move::new((6, 0), (5, 2));
+        tt.store(1, 6, Bound::Exact, 10, Some(m), 0);
+        // a shallower result for another position in the same search does not replace it
+        tt.store(other, 2, Bound::Exact, 20, None, 0);
+        assert_eq!(tt.probe(1, 0).unwrap().depth, 6);
+        assert_eq!(tt.probe(other, 0), None);
+        // a new result for the same position does, keeping the old best move if it has none
+        tt.store(1, 2, Bound::Upper, -5, None, 0);
+        let e = tt.probe(1, 0).unwrap();
+        assert_eq!((e.depth, e.best_move), (2, Some(m)));
+        // and anything from an older search makes way
+        tt.store(1, 9, Bound::Exact, 10, Some(m), 0);
+        tt.new_search();
+        tt.store(other, 1, Bound::Lower, 30, None, 0);
+        assert_eq!(tt.probe(other, 0).unwrap().score, 30);
+        assert_eq!(tt.probe(1, 0), None);
+    }
+
+    #[test]
+    fn hashfull_test() {
+        let mut tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+        for hash in 0..250 {
+            tt.store(hash, 1, Bound::Exact, 0, None, 0);
+        }
+        assert_eq!(tt.hashfull(), 250);
+        // only the current search counts
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+}
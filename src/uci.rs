@@ -0,0 +1,654 @@
+use crate::bitboard::square;
+use crate::engine::{is_mate_score, Engine, Limits, SearchResult, MATE};
+use crate::game::Game;
+use crate::moves::*;
+use crate::pieces::*;
+use crate::square::Square;
+use crate::tt::DEFAULT_HASH_MB;
+use crate::Board;
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use std::{error, fmt};
+
+const ENGINE_NAME: &str = "chess";
+const ENGINE_AUTHOR: &str = "KermitPurple";
+/// Time kept in hand so the move reaches the GUI before the clock runs out
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+/// Moves to spread the clock over when the GUI does not say how many are left
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/// Largest transposition table the `Hash` option allows, in megabytes
+const MAX_HASH_MB: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciMoveError {
+    /// Not shaped like `e2e4` or `e7e8q`
+    Invalid(String),
+    Illegal(MoveError),
+}
+
+impl fmt::Display for UciMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UciMoveError::Invalid(s) => write!(f, "'{}' is not a move like e2e4", s),
+            UciMoveError::Illegal(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for UciMoveError {}
+
+impl Move {
+    /// Write the move in the long algebraic form UCI uses, such as `e2e4`, `e1g1` or `e7e8q`
+    pub fn to_uci(&self) -> String {
+        let mut uci = format!("{}{}", square(self.from), square(self.to));
+        if let Some(typ) = self.promotion {
+            uci.push(typ.letter().to_ascii_lowercase());
+        }
+        uci
+    }
+}
+
+impl Board {
+    /// Read a move in UCI long algebraic form and check it is legal for the side to move
+    pub fn parse_uci(&self, s: &str) -> Result<Move, UciMoveError> {
+        let invalid = || UciMoveError::Invalid(s.to_string());
+        if !s.is_ascii() || !(4..=5).contains(&s.len()) {
+            return Err(invalid());
+        }
+        let from = s[0..2].parse::<Square>().map_err(|_| invalid())?;
+        let to = s[2..4].parse::<Square>().map_err(|_| invalid())?;
+        let promotion = match s[4..].chars().next() {
+            Some(c @ ('q' | 'r' | 'b' | 'n')) => PieceType::from_letter(c.to_ascii_uppercase()),
+            Some(_) => return Err(invalid()),
+            None => None,
+        };
+        self.check_move(Move {
+            promotion,
+            ..Move::new(from.into(), to.into())
+        })
+        .map_err(UciMoveError::Illegal)
+    }
+}
+
+/// The search limits and clock readings of a `go` command
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GoOptions {
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    /// Search until told to stop
+    pub infinite: bool,
+}
+
+impl GoOptions {
+    /// Read the arguments after `go`, skipping anything not understood
+    pub fn parse(args: &[&str]) -> Self {
+        let mut go = GoOptions::default();
+        let mut args = args.iter();
+        while let Some(&arg) = args.next() {
+            if arg == "infinite" {
+                go.infinite = true;
+                continue;
+            }
+            let Some(value) = args.clone().next().and_then(|v| v.parse::<u64>().ok()) else {
+                continue;
+            };
+            let millis = Some(Duration::from_millis(value));
+            match arg {
+                "wtime" => go.wtime = millis,
+                "btime" => go.btime = millis,
+                "winc" => go.winc = millis,
+                "binc" => go.binc = millis,
+                "movetime" => go.movetime = millis,
+                "movestogo" => go.movestogo = Some(value as u32),
+                "depth" => go.depth = Some(value as u32),
+                "nodes" => go.nodes = Some(value),
+                _ => continue,
+            }
+            args.next();
+        }
+        go
+    }
+
+    /// How long to think for, given whose clock is running
+    pub fn limits(&self, turn: Color) -> Limits {
+        let (time, inc) = match turn {
+            Color::White => (self.wtime, self.winc),
+            Color::Black => (self.btime, self.binc),
+        };
+        let time = if self.infinite {
+            None
+        } else if let Some(movetime) = self.movetime {
+            Some(movetime.saturating_sub(MOVE_OVERHEAD))
+        } else {
+            time.map(|time| {
+                let moves = self.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+                let budget = time / moves + inc.unwrap_or_default() * 3 / 4;
+                budget.min(time.saturating_sub(MOVE_OVERHEAD))
+            })
+        };
+        Limits {
+            depth: self.depth,
+            time,
+            nodes: self.nodes,
+        }
+    }
+}
+
+/// Write a score for the side to move the way `info score` wants it,
+/// with mates counted in moves rather than plies
+pub fn score_to_uci(score: i32) -> String {
+    if is_mate_score(score) {
+        let moves = (MATE - score.abs() + 1) / 2;
+        format!("mate {}", if score > 0 { moves } else { -moves })
+    } else {
+        format!("cp {}", score)
+    }
+}
+
+fn info(result: &SearchResult) -> String {
+    let millis = result.time.as_millis() as u64;
+    let pv = result
+        .pv
+        .iter()
+        .map(|m| m.to_uci())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "info depth {} score {} nodes {} time {} nps {} hashfull {} pv {}",
+        result.depth,
+        score_to_uci(result.score),
+        result.nodes,
+        millis,
+        result.nodes * 1000 / millis.max(1),
+        result.hashfull,
+        pv
+    )
+}
+
+/// Write a line to the GUI. Once a write fails the GUI has gone away, so the
+/// search is stopped rather than left thinking for nobody.
+fn send<W: Write>(out: &Mutex<W>, stop: &AtomicBool, line: &str) {
+    let mut out = out.lock().expect("output lock poisoned");
+    if writeln!(out, "{}", line).and_then(|_| out.flush()).is_err() {
+        stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Read the arguments of a `position` command, keeping the moves as a game so
+/// the search knows which positions would repeat
+fn parse_position(args: &[&str]) -> Result<Game, String> {
+    let moves_at = args
+        .iter()
+        .position(|&a| a == "moves")
+        .unwrap_or(args.len());
+    let board = match args.first() {
+        Some(&"startpos") => Board::new(),
+        Some(&"fen") => Board::from_fen(&args[1..moves_at].join(" ")).map_err(|e| e.to_string())?,
+        _ => return Err("position needs startpos or fen".to_string()),
+    };
+    let mut game = Game::from_board(board);
+    for s in args.iter().skip(moves_at + 1) {
+        let m = game.board().parse_uci(s).map_err(|e| e.to_string())?;
+        game.play(m).map_err(|e| e.to_string())?;
+    }
+    Ok(game)
+}
+
+/// Talks to a chess GUI using the Universal Chess Interface. Searches run on
+/// their own thread so `stop` and `isready` are answered while the engine thinks.
+pub struct Uci<W: Write + Send + 'static> {
+    game: Game,
+    out: Arc<Mutex<W>>,
+    stop: Arc<AtomicBool>,
+    engine: Option<Engine>,
+    search: Option<JoinHandle<Engine>>,
+    infinite: bool,
+}
+
+impl<W: Write + Send + 'static> Uci<W> {
+    pub fn new(out: W) -> Self {
+        let engine = Engine::new();
+        Self {
+            game: Game::new(),
+            out: Arc::new(Mutex::new(out)),
+            stop: engine.stop_flag(),
+            engine: Some(engine),
+            search: None,
+            infinite: false,
+        }
+    }
+
+    /// Act on one line from the GUI. Returns false once told to quit.
+    pub fn handle(&mut self, line: &str) -> bool {
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        let Some((&command, args)) = words.split_first() else {
+            return true;
+        };
+        match command {
+            "uci" => {
+                send(&self.out, &self.stop, &format!("id name {}", ENGINE_NAME));
+                send(
+                    &self.out,
+                    &self.stop,
+                    &format!("id author {}", ENGINE_AUTHOR),
+                );
+                send(
+                    &self.out,
+                    &self.stop,
+                    &format!(
+                        "option name Hash type spin default {} min 1 max {}",
+                        DEFAULT_HASH_MB, MAX_HASH_MB
+                    ),
+                );
+                send(&self.out, &self.stop, "uciok");
+            }
+            "isready" => send(&self.out, &self.stop, "readyok"),
+            "ucinewgame" => {
+                self.stop();
+                self.game = Game::new();
+                self.engine_mut().clear_hash();
+            }
+            "setoption" => {
+                self.stop();
+                self.set_option(args);
+            }
+            "position" => {
+                self.stop();
+                match parse_position(args) {
+                    Ok(game) => self.game = game,
+                    Err(err) => send(&self.out, &self.stop, &format!("info string {}", err)),
+                }
+            }
+            "go" if args.first() == Some(&"perft") => {
+                self.stop();
+                self.perft(args.get(1).and_then(|d| d.parse().ok()).unwrap_or(1));
+            }
+            "go" => self.go(GoOptions::parse(args)),
+            "stop" => self.stop(),
+            "quit" => {
+                self.stop();
+                return false;
+            }
+            _ => send(
+                &self.out,
+                &self.stop,
+                &format!("info string unknown command {}", command),
+            ),
+        }
+        true
+    }
+
+    fn go(&mut self, go: GoOptions) {
+        self.stop();
+        self.stop.store(false, Ordering::Relaxed);
+        self.infinite = go.infinite;
+        let mut engine = self.engine.take().expect("no search is running");
+        let (game, out, stop) = (self.game.clone(), self.out.clone(), self.stop.clone());
+        self.search = Some(thread::spawn(move || {
+            let limits = go.limits(game.board().turn());
+            let result = engine.search_game_with(&game, limits, |r| send(&out, &stop, &info(r)));
+            // an infinite search must not answer before the GUI says stop
+            while go.infinite && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+            let best = match (result.best_move, result.pv.get(1)) {
+                (Some(m), Some(ponder)) => format!("{} ponder {}", m.to_uci(), ponder.to_uci()),
+                (Some(m), None) => m.to_uci(),
+                (None, _) => "0000".to_string(),
+            };
+            send(&out, &stop, &format!("bestmove {}", best));
+            engine
+        }));
+    }
+
+    /// The engine, which is only ever missing while a search has it
+    fn engine_mut(&mut self) -> &mut Engine {
+        self.engine.as_mut().expect("no search is running")
+    }
+
+    /// `setoption name <name> [value <value>]`, names are not case sensitive
+    fn set_option(&mut self, args: &[&str]) {
+        let value_at = args.iter().position(|&w| w == "value");
+        let name = args[..value_at.unwrap_or(args.len())]
+            .iter()
+            .skip_while(|&&w| w == "name")
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let value = value_at.map(|i| args[i + 1..].join(" "));
+        match (name.to_lowercase().as_str(), value) {
+            ("hash", Some(value)) => match value.parse::<usize>() {
+                Ok(mb) if (1..=MAX_HASH_MB).contains(&mb) => self.engine_mut().set_hash_size(mb),
+                _ => send(
+                    &self.out,
+                    &self.stop,
+                    &format!(
+                        "info string Hash must be from 1 to {}, not {}",
+                        MAX_HASH_MB, value
+                    ),
+                ),
+            },
+            _ => send(
+                &self.out,
+                &self.stop,
+                &format!("info string unknown option {}", name),
+            ),
+        }
+    }
+
+    /// Count moves for each first move the way other engines' `go perft` does,
+    /// for comparing move generators
+    fn perft(&self, depth: u32) {
+        let mut total = 0;
+        for (m, nodes) in self.game.board().divide(depth) {
+            send(&self.out, &self.stop, &format!("{}: {}", m.to_uci(), nodes));
+            total += nodes;
+        }
+        send(&self.out, &self.stop, "");
+        send(&self.out, &self.stop, &format!("Nodes searched: {}", total));
+    }
+
+    /// Wait for the running search, if any, after asking it to stop
+    fn stop(&mut self) {
+        if self.search.is_some() {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        self.join();
+    }
+
+    fn join(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.engine = Some(search.join().expect("search thread panicked"));
+        }
+    }
+
+    /// Let a running search finish on its own, stopping it only if it never would
+    pub fn finish(&mut self) {
+        if self.infinite {
+            self.stop();
+        } else {
+            self.join();
+        }
+    }
+}
+
+/// Speak UCI until `quit` or the end of input
+pub fn run<W: Write + Send + 'static>(input: impl BufRead, output: W) {
+    let mut uci = Uci::new(output);
+    for line in input.lines() {
+        let Ok(line) = line else { break };
+        if !uci.handle(&line) {
+            return;
+        }
+    }
+    uci.finish();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uci_move_test() {
+        let b = Board::new();
+        let m = b.parse_uci("e2e4").unwrap();
+        assert_eq!(m, Move::new((4, 1), (4, 3)));
+        assert_eq!(m.to_uci(), "e2e4");
+        assert_eq!(
+            b.parse_uci("e2e5"),
+            Err(UciMoveError::Illegal(MoveError::IllegalMove))
+        );
+        assert_eq!(
+            b.parse_uci("e7e5"),
+            Err(UciMoveError::Illegal(MoveError::WrongColor))
+        );
+        for s in ["", "e2", "e2e", "e2e4qq", "e2x4", "e2e4k", "e2e4Q", "i2e4"] {
+            assert_eq!(b.parse_uci(s), Err(UciMoveError::Invalid(s.to_string())));
+        }
+        for m in b.legal_moves(b.turn()) {
+            assert_eq!(b.parse_uci(&m.to_uci()), Ok(m));
+        }
+    }
+
+    #[test]
+    fn uci_special_moves_test() {
+        let b = Board::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let castle = b.parse_uci("e1g1").unwrap();
+        assert!(castle.flags.castle);
+        assert_eq!(castle.to_uci(), "e1g1");
+        assert!(b.parse_uci("e1c1").unwrap().flags.castle);
+        let promote = b.parse_uci("b7a8n").unwrap();
+        assert_eq!(promote.promotion, Some(PieceType::Knight));
+        assert!(promote.flags.capture);
+        assert_eq!(promote.to_uci(), "b7a8n");
+        assert_eq!(
+            b.parse_uci("b7b8"),
+            Err(UciMoveError::Illegal(MoveError::MissingPromotion))
+        );
+        assert_eq!(
+            b.parse_uci("a1a2q"),
+            Err(UciMoveError::Illegal(MoveError::InvalidPromotion))
+        );
+        for m in b.legal_moves(b.turn()) {
+            assert_eq!(b.parse_uci(&m.to_uci()), Ok(m));
+        }
+    }
+
+    /// A writer the test can still read after handing it to `Uci`
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuf {
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone())
+                .unwrap()
+                .lines()
+                .map(String::from)
+                .collect()
+        }
+    }
+
+    /// A GUI that has closed its end of the pipe
+    struct Closed;
+
+    impl Write for Closed {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn session(script: &str) -> Vec<String> {
+        let buf = SharedBuf::default();
+        run(script.as_bytes(), buf.clone());
+        buf.lines()
+    }
+
+    #[test]
+    fn handshake_test() {
+        let out = session("uci\nisready\nfoo\nquit\nisready\n");
+        assert_eq!(
+            out,
+            [
+                "id name chess",
+                "id author KermitPurple",
+                "option name Hash type spin default 16 min 1 max 1024",
+                "uciok",
+                "readyok",
+                "info string unknown command foo",
+            ]
+        );
+    }
+
+    #[test]
+    fn position_test() {
+        let mut uci = Uci::new(SharedBuf::default());
+        uci.handle("position startpos moves e2e4 e7e5 g1f3");
+        assert_eq!(
+            uci.game.board().to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        uci.handle("position fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1 moves e1g1 e8c8");
+        assert_eq!(
+            uci.game.board().to_fen(),
+            "2kr3r/8/8/8/8/8/8/R4RK1 w - - 2 2"
+        );
+        // a bad position leaves the old one alone
+        let before = *uci.game.board();
+        uci.handle("position startpos moves e2e5");
+        uci.handle("position fen 8/8 w - - 0 1");
+        assert_eq!(*uci.game.board(), before);
+    }
+
+    #[test]
+    fn go_test() {
+        let out = session(
+            "position fen r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 4 4\n\
+             go depth 3\n",
+        );
+        assert!(out[0].starts_with("info depth 1 score mate 1 nodes "));
+        assert!(out[0].ends_with(" pv f3f7"));
+        assert_eq!(out.last().unwrap(), "bestmove f3f7");
+
+        let out = session("position fen 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1\ngo depth 2\n");
+        assert_eq!(out, ["bestmove 0000"]);
+    }
+
+    #[test]
+    fn repetition_test() {
+        let out = session(
+            "position fen 7k/q7/8/8/8/8/8/6NK w - - 0 1 moves g1f3 a7a6 f3g1 a6a7\n\
+             go depth 2\n",
+        );
+        assert!(out[1].starts_with("info depth 2 score cp 0 "));
+        assert_eq!(out.last().unwrap(), "bestmove g1f3");
+    }
+
+    #[test]
+    fn option_test() {
+        let mut uci = Uci::new(SharedBuf::default());
+        uci.handle("setoption name Hash value 1");
+        assert_eq!(uci.engine_mut().hashfull(), 0);
+        uci.handle("go depth 3");
+        uci.finish();
+        assert!(uci.engine_mut().hashfull() > 0);
+        uci.handle("ucinewgame");
+        assert_eq!(uci.engine_mut().hashfull(), 0);
+
+        let out = session("setoption name Hash value 0\nsetoption name Ponder value true\n");
+        assert_eq!(
+            out,
+            [
+                "info string Hash must be from 1 to 1024, not 0",
+                "info string unknown option Ponder",
+            ]
+        );
+        let out = session("go depth 1\n");
+        assert!(out[0].contains(" hashfull "));
+    }
+
+    #[test]
+    fn perft_test() {
+        let out = session("position startpos moves e2e4\ngo perft 2\n");
+        assert_eq!(out.len(), 22);
+        assert!(out.contains(&"e7e5: 29".to_string()));
+        assert_eq!(out.last().unwrap(), "Nodes searched: 600");
+    }
+
+    #[test]
+    fn closed_pipe_test() {
+        let mut uci = Uci::new(Closed);
+        uci.handle("position startpos");
+        uci.handle("go infinite");
+        // the first info line cannot be sent, which ends even an infinite search
+        let search = uci.search.take().unwrap();
+        assert!(search.join().is_ok());
+        assert!(uci.stop.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn stop_test() {
+        let buf = SharedBuf::default();
+        let mut uci = Uci::new(buf.clone());
+        uci.handle("position startpos");
+        uci.handle("go infinite");
+        thread::sleep(Duration::from_millis(50));
+        uci.handle("isready");
+        assert!(buf.lines().contains(&"readyok".to_string()));
+        assert!(!buf.lines().iter().any(|l| l.starts_with("bestmove")));
+        uci.handle("stop");
+        let out = buf.lines();
+        assert!(out.last().unwrap().starts_with("bestmove "));
+        // another search can follow
+        uci.handle("go depth 1");
+        uci.handle("quit");
+        assert_eq!(
+            buf.lines()
+                .iter()
+                .filter(|l| l.starts_with("bestmove"))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn go_options_test() {
+        let go = GoOptions::parse(
+            &"wtime 60000 btime 30000 winc 1000 binc 0 movestogo 10"
+                .split_whitespace()
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(go.wtime, Some(Duration::from_secs(60)));
+        assert_eq!(go.movestogo, Some(10));
+        assert_eq!(
+            go.limits(Color::White).time,
+            Some(Duration::from_millis(6750))
+        );
+        assert_eq!(go.limits(Color::Black).time, Some(Duration::from_secs(3)));
+
+        let go = GoOptions::parse(&["ponder", "movetime", "500", "depth", "7", "nodes", "x"]);
+        let limits = go.limits(Color::Black);
+        assert_eq!(limits.time, Some(Duration::from_millis(450)));
+        assert_eq!(limits.depth, Some(7));
+        assert_eq!(limits.nodes, None);
+
+        // never plan past the end of the clock
+        let go = GoOptions::parse(&["btime", "40", "binc", "1000"]);
+        assert_eq!(go.limits(Color::Black).time, Some(Duration::ZERO));
+        assert_eq!(
+            GoOptions::parse(&["infinite"]).limits(Color::White),
+            Limits::default()
+        );
+    }
+
+    #[test]
+    fn score_test() {
+        assert_eq!(score_to_uci(35), "cp 35");
+        assert_eq!(score_to_uci(-120), "cp -120");
+        assert_eq!(score_to_uci(MATE - 1), "mate 1");
+        assert_eq!(score_to_uci(MATE - 3), "mate 2");
+        assert_eq!(score_to_uci(-(MATE - 2)), "mate -1");
+    }
+}
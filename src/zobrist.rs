@@ -0,0 +1,176 @@
+use crate::bitboard::{color_index, square, type_index};
+use crate::pieces::*;
+use crate::{Board, CastleRights, Position};
+
+/// Fixed pseudo random numbers, so a position hashes the same in every run and every build
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (state, z ^ (z >> 31))
+}
+
+struct Keys {
+    /// One for each piece on each square, white pieces first in `PieceType` order
+    pieces: [[u64; 64]; 12],
+    black_to_move: u64,
+    /// White left, white right, black left, black right
+    castling: [u64; 4],
+    /// By the file of the pawn that can be taken en passant
+    passant: [u64; 8],
+}
+
+const KEYS: Keys = {
+    let mut keys = Keys {
+        pieces: [[0; 64]; 12],
+        black_to_move: 0,
+        castling: [0; 4],
+        passant: [0; 8],
+    };
+    let mut state = 0x2545_f491_4f6c_dd1d;
+    let mut piece = 0;
+    while piece < 12 {
+        let mut sq = 0;
+        while sq < 64 {
+            let (next, key) = splitmix64(state);
+            state = next;
+            keys.pieces[piece][sq] = key;
+            sq += 1;
+        }
+        piece += 1;
+    }
+    let (next, key) = splitmix64(state);
+    state = next;
+    keys.black_to_move = key;
+    let mut i = 0;
+    while i < 4 {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys.castling[i] = key;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 8 {
+        let (next, key) = splitmix64(state);
+        state = next;
+        keys.passant[i] = key;
+        i += 1;
+    }
+    keys
+};
+
+pub(crate) fn piece_key(piece: Piece, pos: Position) -> u64 {
+    KEYS.pieces[color_index(piece.color) * 6 + type_index(piece.typ)][square(pos).index()]
+}
+
+pub(crate) fn turn_key(turn: Color) -> u64 {
+    match turn {
+        Color::White => 0,
+        Color::Black => KEYS.black_to_move,
+    }
+}
+
+pub(crate) fn castle_key(rights: CastleRights) -> u64 {
+    [
+        rights.white_left,
+        rights.white_right,
+        rights.black_left,
+        rights.black_right,
+    ]
+    .into_iter()
+    .zip(KEYS.castling)
+    .filter(|&(allowed, _)| allowed)
+    .fold(0, |hash, (_, key)| hash ^ key)
+}
+
+pub(crate) fn passant_key(passant_killable: Option<Position>) -> u64 {
+    passant_killable.map_or(0, |(x, _)| KEYS.passant[x])
+}
+
+impl Board {
+    /// A Zobrist hash of the pieces, side to move, castling rights and en passant pawn.
+    /// Positions that are the same for the repetition rules hash the same, whatever the clocks say.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Work out `hash` from nothing, rather than keeping it up to date move by move
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = turn_key(self.turn)
+            ^ castle_key(self.castle_rights)
+            ^ passant_key(self.passant_killable);
+        for y in 0..8 {
+            for x in 0..8 {
+                if let Some(p) = self.board[y][x] {
+                    hash ^= piece_key(p, (x, y));
+                }
+            }
+        }
+        hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::moves::Move;
+
+    /// Play every line a few moves deep, checking the hash kept by `make_move`
+    /// against one worked out from scratch after each move
+    fn walk(board: &Board, depth: u32) {
+        assert_eq!(board.hash(), board.compute_hash(), "{}", board.to_fen());
+        if depth > 0 {
+            for m in board.legal_moves(board.turn()) {
+                walk(&board.test_move(m), depth - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_hash_test() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ] {
+            walk(&Board::from_fen(fen).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn hash_identity_test() {
+        let start = Board::new();
+        // the keys never change, so hashes can be stored and compared later
+        assert_eq!(start.hash(), 0x5cf7_6ce8_b7b5_d814);
+        assert_eq!(Board::default().hash(), Board::default().compute_hash());
+
+        // the knights go out and come back, the clocks differ but the position is the same
+        let mut b = start;
+        for (from, to) in [
+            ((6, 0), (5, 2)),
+            ((6, 7), (5, 5)),
+            ((5, 2), (6, 0)),
+            ((5, 5), (6, 7)),
+        ] {
+            b.play(Move::new(from, to)).unwrap();
+        }
+        assert_ne!(b, start);
+        assert_eq!(b.hash(), start.hash());
+
+        let mut b = start;
+        b.play(Move::new((6, 0), (5, 2))).unwrap();
+        let white =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R w KQkq - 0 1").unwrap();
+        assert_ne!(b.hash(), white.hash());
+        let no_castle =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b Qkq - 0 1").unwrap();
+        assert_ne!(b.hash(), no_castle.hash());
+        let passant = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        let no_passant = Board::from_fen("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+        assert_ne!(passant.hash(), no_passant.hash());
+    }
+}